@@ -0,0 +1,261 @@
+//! Inspects and repairs the database while the bot is down
+//!
+//! Don't run this at the same time as the bot, neither of them expects someone
+//! else to be writing to the database. Like the bot, set
+//! `QUICKSILVER_STORAGE=sqlite` to open an SQLite database.
+
+use clap::{Parser, Subcommand};
+use eyre::{eyre, Result};
+use quicksilver::{
+	config,
+	data::{
+		envelope::{EventEnvelope, EventMeta, EventSource},
+		items::InventoryItem,
+		schema,
+		state::DBEvent,
+		Database,
+	},
+};
+use serenity::all::{GuildId, UserId};
+use std::path::PathBuf;
+
+#[derive(Parser)]
+struct Args {
+	/// Where the database is, defaults to where the bot keeps it
+	#[arg(long)]
+	path: Option<PathBuf>,
+
+	#[command(subcommand)]
+	command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+	/// Print a user's progress
+	User {
+		user: u64,
+
+		/// The guild to look in, leave out for the global profile
+		#[arg(long)]
+		guild: Option<u64>,
+	},
+
+	/// List events in the live timeline
+	Events {
+		/// Only events about this user
+		#[arg(long)]
+		user: Option<u64>,
+
+		/// Only events that happened in this guild
+		#[arg(long)]
+		guild: Option<u64>,
+
+		/// Only events of this kind, like `AdminGive`
+		#[arg(long)]
+		kind: Option<String>,
+
+		/// Only the last few matching events
+		#[arg(long)]
+		last: Option<usize>,
+	},
+
+	/// Check that the timeline replays to the state the database loaded
+	Validate,
+
+	/// Print the current state as JSON
+	Export,
+
+	/// Start an empty database from a state printed by `export`
+	Import { file: PathBuf },
+
+	/// Give a user an item
+	Give {
+		user: u64,
+		item: String,
+
+		#[arg(long)]
+		guild: Option<u64>,
+	},
+
+	/// Take an item from a user
+	Burn {
+		user: u64,
+		item: String,
+
+		#[arg(long)]
+		guild: Option<u64>,
+	},
+
+	/// Revert an event, by its index
+	Revert { index: usize },
+
+	/// Work out everyone's level in a guild again from their lifetime XP,
+	/// optionally on a new curve like `linear 100 10`
+	Recompute {
+		guild: u64,
+
+		#[arg(long)]
+		curve: Option<String>,
+	},
+}
+
+fn main() -> Result<()> {
+	let args = Args::parse();
+
+	let backend = config::get_storage_backend();
+
+	let path = args
+		.path
+		.unwrap_or_else(|| backend.default_path().to_path_buf());
+
+	// Importing is the only way to start a database from here
+	if !matches!(args.command, Command::Import { .. }) && !path.exists() {
+		return Err(eyre!("there's no database at {}", path.display()));
+	}
+
+	// Looking around shouldn't change anything, not even by loading
+	let mut db = match args.command {
+		Command::User { .. } | Command::Events { .. } | Command::Validate | Command::Export => {
+			Database::inspect(backend.open_read_only(&path)?)?
+		}
+		_ => Database::new(backend.open_at(&path)?)?,
+	};
+
+	match args.command {
+		Command::User { user, guild } => {
+			let db_user = db
+				.state()
+				.get_user_or_default(guild.map(GuildId::new), &UserId::new(user));
+
+			println!("{}", serde_json::to_string_pretty(&db_user)?);
+		}
+		Command::Events {
+			user,
+			guild,
+			kind,
+			last,
+		} => {
+			let reverted = db.reverted();
+
+			let mut lines = db
+				.timeline()
+				.iter()
+				.enumerate()
+				.map(|(idx, envelope)| (db.len() - db.timeline().len() + idx, envelope))
+				.filter(|(_, x)| user.is_none_or(|id| x.subject() == Some(UserId::new(id))))
+				.filter(|(_, x)| guild.is_none_or(|id| x.meta.guild == Some(GuildId::new(id))))
+				.filter(|(_, x)| kind.as_ref().is_none_or(|kind| *kind == event_kind(x)))
+				.map(|(idx, x)| {
+					format!(
+						"#{idx} {} {:?}{} {:?}",
+						x.meta.timestamp,
+						x.meta.source,
+						if reverted.contains(&idx) {
+							" (reverted)"
+						} else {
+							""
+						},
+						x.event
+					)
+				})
+				.collect::<Vec<_>>();
+
+			if let Some(last) = last {
+				lines.drain(..lines.len().saturating_sub(last));
+			}
+
+			for line in lines {
+				println!("{line}");
+			}
+		}
+		Command::Validate => {
+			db.validate()?;
+
+			println!(
+				"{} events ({} compacted, {} reverted) replay cleanly",
+				db.len(),
+				db.len() - db.timeline().len(),
+				db.reverted().len()
+			);
+		}
+		Command::Export => println!("{}", serde_json::to_string_pretty(db.state())?),
+		Command::Import { file } => {
+			let state =
+				schema::decode_state(serde_json::from_str(&std::fs::read_to_string(file)?)?)?;
+
+			db.import(
+				state,
+				EventMeta::now(EventSource::System("admin_cli".to_string()), None, None),
+			)?;
+
+			println!("Imported into {}", path.display());
+		}
+		Command::Give { user, item, guild } => {
+			add(
+				&mut db,
+				DBEvent::AdminGive {
+					user: UserId::new(user),
+					item: parse_item(&item)?,
+				},
+				guild,
+			)?;
+		}
+		Command::Burn { user, item, guild } => {
+			add(
+				&mut db,
+				DBEvent::AdminBurn {
+					user: UserId::new(user),
+					item: parse_item(&item)?,
+				},
+				guild,
+			)?;
+		}
+		Command::Revert { index } => add(&mut db, DBEvent::Revert { index }, None)?,
+		Command::Recompute { guild, curve } => {
+			let server = GuildId::new(guild);
+
+			let mut leveling = db.state().get_server_or_default(&server).leveling;
+
+			if let Some(curve) = curve {
+				leveling.curve = curve.parse()?;
+			}
+
+			add(
+				&mut db,
+				DBEvent::SetLeveling { server, leveling },
+				Some(guild),
+			)?;
+		}
+	}
+
+	Ok(())
+}
+
+fn add(db: &mut Database, event: DBEvent, guild: Option<u64>) -> Result<()> {
+	let meta = EventMeta::now(
+		EventSource::System("admin_cli".to_string()),
+		None,
+		guild.map(GuildId::new),
+	);
+
+	db.add(event, meta)?;
+
+	println!("Added event #{}", db.len() - 1);
+
+	Ok(())
+}
+
+/// Items are written the way they're stored, like `ScytheVivi`
+fn parse_item(name: &str) -> Result<InventoryItem> {
+	serde_json::from_value(serde_json::Value::String(name.to_string()))
+		.map_err(|_| eyre!("there is no item called {name}"))
+}
+
+/// The name of the event's variant, like `AdminGive`
+fn event_kind(envelope: &EventEnvelope) -> String {
+	match serde_json::to_value(&envelope.event) {
+		Ok(serde_json::Value::Object(x)) => x.keys().next().cloned().unwrap_or_default(),
+		Ok(serde_json::Value::String(x)) => x,
+		_ => String::new(),
+	}
+}
@@ -0,0 +1,72 @@
+use crate::{
+	data::{announcements::AnnouncementMode, state::DBEvent},
+	systems::autoconfig::apply_config::update_config,
+	utils::{Admin, GetDB, Meta},
+	Context, Error,
+};
+use eyre::Result;
+use poise::ChoiceParameter;
+
+/// Change where and how level-ups are announced in this server
+#[poise::command(slash_command, guild_only)]
+pub async fn admin_announcements(
+	ctx: Context<'_>,
+	#[description = "Where level-ups go"] mode: Option<AnnouncementMode>,
+	#[description = "Can use {user}, {previous}, {level} and {xp}"] template: Option<String>,
+	#[description = "Attach the user's profile card"] card: Option<bool>,
+) -> Result<(), Error> {
+	ctx.defer_ephemeral().await?;
+
+	if !ctx.author().is_admin() {
+		ctx.say("You are not an admin.").await?;
+		return Ok(());
+	}
+
+	let Some(server) = ctx.guild_id() else {
+		return Ok(());
+	};
+
+	let mut config = ctx
+		.db("admin announcements")
+		.await
+		.state()
+		.get_server_or_default(&server)
+		.announcements;
+
+	let mode_changed = mode.is_some_and(|x| x != config.mode);
+
+	if let Some(mode) = mode {
+		config.mode = mode;
+	}
+
+	if let Some(template) = template {
+		config.template = template;
+	}
+
+	if let Some(card) = card {
+		config.card = card;
+	}
+
+	ctx.db("admin announcements").await.add(
+		DBEvent::SetAnnouncements {
+			server,
+			config: config.clone(),
+		},
+		ctx.meta(),
+	)?;
+
+	// Makes or deletes the level-ups channel
+	if mode_changed {
+		update_config(&ctx, &server).await?;
+	}
+
+	ctx.say(format!(
+		"Level-ups go to: {}{}\nPreview: {}",
+		config.mode.name(),
+		if config.card { ", with a card" } else { "" },
+		config.render(ctx.author().id, 4, 5, 112)
+	))
+	.await?;
+
+	Ok(())
+}
@@ -0,0 +1,76 @@
+use crate::{
+	data::state::DBEvent,
+	utils::{Admin, GetDB, Meta},
+	Context, Error,
+};
+use eyre::Result;
+
+/// Change how this server limits XP from messages, leave options out to keep
+/// them
+#[poise::command(slash_command, guild_only)]
+pub async fn admin_anti_spam(
+	ctx: Context<'_>,
+	#[description = "Seconds between messages that give XP"] cooldown: Option<u64>,
+	#[description = "Keep a separate cooldown for every channel"] per_channel: Option<bool>,
+	#[description = "Most XP per hour, 0 for no cap"] hourly_cap: Option<u64>,
+	#[description = "How close (in bits) a message can be to the last one, 0 to allow repeats"]
+	duplicate_distance: Option<u32>,
+) -> Result<(), Error> {
+	ctx.defer_ephemeral().await?;
+
+	if !ctx.author().is_admin() {
+		ctx.say("You are not an admin.").await?;
+		return Ok(());
+	}
+
+	let Some(server) = ctx.guild_id() else {
+		return Ok(());
+	};
+
+	let mut db = ctx.db("admin anti spam").await;
+
+	let mut config = db.state().anti_spam(Some(server));
+
+	if let Some(cooldown) = cooldown {
+		config.cooldown = cooldown;
+	}
+
+	if let Some(per_channel) = per_channel {
+		config.per_channel = per_channel;
+	}
+
+	if let Some(hourly_cap) = hourly_cap {
+		config.hourly_cap = Some(hourly_cap).filter(|x| *x > 0);
+	}
+
+	if let Some(duplicate_distance) = duplicate_distance {
+		config.duplicate_distance = Some(duplicate_distance).filter(|x| *x > 0);
+	}
+
+	db.add(
+		DBEvent::SetAntiSpam {
+			server,
+			config: config.clone(),
+		},
+		ctx.meta(),
+	)?;
+
+	ctx.say(format!(
+		"Cooldown: {}s{}\nHourly cap: {}\nDuplicate distance: {}",
+		config.cooldown,
+		if config.per_channel {
+			" per channel"
+		} else {
+			""
+		},
+		config
+			.hourly_cap
+			.map_or("none".to_string(), |x| format!("{x} XP")),
+		config
+			.duplicate_distance
+			.map_or("off".to_string(), |x| format!("{x} bits")),
+	))
+	.await?;
+
+	Ok(())
+}
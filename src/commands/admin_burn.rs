@@ -1,9 +1,9 @@
 use crate::{
 	data::{
 		items::InventoryItem,
-		state::{DBEvent, SideChannel},
+		state::{DBEvent, ReduceError},
 	},
-	utils::{Admin, GetDB},
+	utils::{Admin, GetDB, Meta},
 	Context, Error,
 };
 use eyre::Result;
@@ -20,19 +20,24 @@ pub async fn admin_burn(ctx: Context<'_>, user: User, item: InventoryItem) -> Re
 
 	let mut db = ctx.db("admin burn").await;
 
-	let err = db.add(DBEvent::AdminBurn {
-		user: user.id,
-		item,
-	})?;
+	let result = db.add(
+		DBEvent::AdminBurn {
+			user: user.id,
+			item,
+		},
+		ctx.meta(),
+	);
 
-	match err {
-		SideChannel::AdminBurnFail { user_error } => {
-			ctx.say(format!("Error: {user_error}")).await?;
-		}
-		SideChannel::None => {
+	match result {
+		Ok(_) => {
 			ctx.say("Burned").await?;
 		}
-		state => panic!("Expected AdminBurnFail | None but got {:?}", state),
+		Err(err) => match err.downcast_ref::<ReduceError>() {
+			Some(reduce_error) => {
+				ctx.say(format!("Error: {reduce_error}")).await?;
+			}
+			None => return Err(err.into()),
+		},
 	}
 
 	Ok(())
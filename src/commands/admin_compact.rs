@@ -0,0 +1,29 @@
+use crate::{
+	utils::{Admin, GetDB},
+	Context, Error,
+};
+use eyre::Result;
+
+/// Fold the timeline into a snapshot and archive the raw events
+#[poise::command(slash_command)]
+pub async fn admin_compact(ctx: Context<'_>) -> Result<(), Error> {
+	ctx.defer_ephemeral().await?;
+
+	if !ctx.author().is_admin() {
+		ctx.say("You are not an admin.").await?;
+		return Ok(());
+	}
+
+	let mut db = ctx.db("admin compact").await;
+
+	let archive = db.compact()?;
+
+	ctx.say(format!(
+		"Compacted {} events, raw history archived to {}",
+		db.len(),
+		archive
+	))
+	.await?;
+
+	Ok(())
+}
@@ -0,0 +1,71 @@
+use crate::{
+	data::{items::InventoryItem, state::DBEvent},
+	utils::{Admin, GetDB, Meta},
+	Context, Error,
+};
+use eyre::Result;
+
+/// End the season, archiving everyone's standings and starting over
+#[poise::command(slash_command, guild_only)]
+pub async fn admin_end_season(
+	ctx: Context<'_>,
+	#[description = "An item for the best players of the season"] reward: Option<InventoryItem>,
+	#[description = "How many players get the reward, 3 by default"]
+	#[min = 1]
+	#[max = 100]
+	winners: Option<usize>,
+) -> Result<(), Error> {
+	ctx.defer_ephemeral().await?;
+
+	if !ctx.author().is_admin() {
+		ctx.say("You are not an admin.").await?;
+		return Ok(());
+	}
+
+	let rewards = match reward {
+		Some(item) => vec![item; winners.unwrap_or(3)],
+		None => vec![],
+	};
+
+	let mut db = ctx.db("admin end season").await;
+
+	// Ending the season there would reset every guild that shares it
+	if ctx
+		.guild_id()
+		.is_some_and(|x| db.state().get_server_or_default(&x).global_profile)
+	{
+		ctx.say("This server uses the global profile, whose season is shared with other servers and can't be ended from here.")
+			.await?;
+		return Ok(());
+	}
+
+	db.add(DBEvent::EndSeason { rewards }, ctx.meta())?;
+
+	let progress = db.state().progress(ctx.guild_id());
+
+	let Some(season) = progress.seasons.last() else {
+		return Ok(());
+	};
+
+	let podium = season
+		.standings
+		.iter()
+		.take(3)
+		.enumerate()
+		.map(|(rank, x)| format!("{}. <@{}>, level {}", rank + 1, x.user, x.level))
+		.collect::<Vec<_>>();
+
+	ctx.say(format!(
+		"Season {} is over, season {} starts now.\n{}",
+		season.number,
+		progress.season(),
+		if podium.is_empty() {
+			"Nobody gained any XP.".to_string()
+		} else {
+			podium.join("\n")
+		}
+	))
+	.await?;
+
+	Ok(())
+}
@@ -1,6 +1,6 @@
 use crate::{
 	data::{items::InventoryItem, state::DBEvent},
-	utils::{Admin, GetDB},
+	utils::{Admin, GetDB, Meta},
 	Context, Error,
 };
 use eyre::Result;
@@ -17,10 +17,13 @@ pub async fn admin_give(ctx: Context<'_>, user: User, item: InventoryItem) -> Re
 
 	let mut db = ctx.db("admin give").await;
 
-	db.add(DBEvent::AdminGive {
-		user: user.id,
-		item,
-	})?;
+	db.add(
+		DBEvent::AdminGive {
+			user: user.id,
+			item,
+		},
+		ctx.meta(),
+	)?;
 
 	ctx.say("Granted").await?;
 
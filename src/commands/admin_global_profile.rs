@@ -0,0 +1,34 @@
+use crate::{
+	data::state::DBEvent,
+	utils::{Admin, GetDB, Meta},
+	Context, Error,
+};
+use eyre::Result;
+
+/// Share XP, items and counters with other servers instead of keeping them here
+#[poise::command(slash_command, guild_only)]
+pub async fn admin_global_profile(ctx: Context<'_>, enabled: bool) -> Result<(), Error> {
+	ctx.defer_ephemeral().await?;
+
+	if !ctx.author().is_admin() {
+		ctx.say("You are not an admin.").await?;
+		return Ok(());
+	}
+
+	let Some(server) = ctx.guild_id() else {
+		return Ok(());
+	};
+
+	let mut db = ctx.db("admin global profile").await;
+
+	db.add(DBEvent::SetGlobalProfile { server, enabled }, ctx.meta())?;
+
+	ctx.say(if enabled {
+		"This server now uses the global profile."
+	} else {
+		"This server now keeps its own progress."
+	})
+	.await?;
+
+	Ok(())
+}
@@ -0,0 +1,139 @@
+use crate::{
+	data::{envelope::EventEnvelope, reputation::RepSource, state::DBEvent, user::DBUser},
+	utils::{Admin, GetDB},
+	Context, Error,
+};
+use eyre::Result;
+use serenity::all::User;
+use std::collections::{BTreeMap, HashSet};
+
+const EVENTS_PER_PAGE: usize = 10;
+
+/// See every event that changed a user, and where it left them
+#[poise::command(slash_command)]
+pub async fn admin_history(ctx: Context<'_>, user: User) -> Result<(), Error> {
+	ctx.defer_ephemeral().await?;
+
+	if !ctx.author().is_admin() {
+		ctx.say("You are not an admin.").await?;
+		return Ok(());
+	}
+
+	// Copied out so the database isn't locked while everything is replayed
+	let history = ctx.db("admin history").await.history()?;
+
+	let mut lines = vec![];
+
+	// Reverts don't name a user, so remember which events were about ours
+	let mut touched = HashSet::new();
+
+	history.replay(|idx, envelope, state| {
+		let about_user = match envelope.event {
+			DBEvent::Revert { index } => touched.contains(&index),
+			// Imports replace everyone's progress
+			DBEvent::Import { .. } => true,
+			// Only show progress kept in the server we're asked from
+			_ => {
+				envelope.subject() == Some(user.id)
+					&& state.progress_owner(envelope.meta.guild)
+						== state.progress_owner(ctx.guild_id())
+			}
+		};
+
+		if !about_user {
+			return;
+		}
+
+		touched.insert(idx);
+
+		lines.push(format!(
+			"`#{idx}` <t:{}:f> {}{}\n-# → {}",
+			envelope.meta.timestamp.unix_timestamp(),
+			describe(envelope),
+			if history.reverted.contains(&idx) {
+				" *(reverted)*"
+			} else {
+				""
+			},
+			summarize(&state.get_user_or_default(ctx.guild_id(), &user.id))
+		));
+	});
+
+	let compacted = match history.compacted {
+		0 => String::new(),
+		x => format!("\n-# Events before `#{x}` were compacted and aren't shown."),
+	};
+
+	if lines.is_empty() {
+		ctx.say(format!("<@{}> has no history.{compacted}", user.id))
+			.await?;
+		return Ok(());
+	}
+
+	let pages = lines
+		.chunks(EVENTS_PER_PAGE)
+		.map(|x| x.join("\n") + &compacted)
+		.collect::<Vec<_>>();
+
+	poise::builtins::paginate(ctx, &pages.iter().map(|x| x.as_str()).collect::<Vec<_>>()).await?;
+
+	Ok(())
+}
+
+fn describe(envelope: &EventEnvelope) -> String {
+	let by = match envelope.meta.actor {
+		Some(actor) => format!(" by <@{actor}>"),
+		None => String::new(),
+	};
+
+	match &envelope.event {
+		DBEvent::Counter { .. } => "Counted".to_string(),
+		DBEvent::CoinFlip { flip } => {
+			format!("Flipped {}", if flip.heads() { "heads" } else { "tails" })
+		}
+		DBEvent::DiceRoll { dice, .. } => format!("Rolled {dice}"),
+		DBEvent::UserSendMessage { length, .. } => format!("Sent a message ({length} letters)"),
+		DBEvent::VoiceSession { active_minutes, .. } => {
+			format!("Talked in voice ({active_minutes} active minutes)")
+		}
+		DBEvent::DailyClaim { .. } => "Claimed their daily reward".to_string(),
+		DBEvent::GiveReputation { giver, source, .. } => match source {
+			RepSource::Command => format!("Given reputation by <@{giver}>"),
+			RepSource::Thanks => format!("Thanked by <@{giver}>"),
+		},
+		DBEvent::AdminGive { item, .. } => format!("Given **{}**{by}", item.info().name),
+		DBEvent::AdminBurn { item, .. } => format!("Burned **{}**{by}", item.info().name),
+		DBEvent::Import { .. } => format!("Progress imported{by}"),
+		DBEvent::Revert { index } => format!("Reverted `#{index}`{by}"),
+		event => format!("{event:?}"),
+	}
+}
+
+fn summarize(user: &DBUser) -> String {
+	let mut counts = BTreeMap::new();
+
+	for item in &user.items {
+		*counts.entry(item.info().name).or_insert(0u64) += 1;
+	}
+
+	let items = if counts.is_empty() {
+		"no items".to_string()
+	} else {
+		counts
+			.iter()
+			.map(|(name, count)| {
+				if *count > 1 {
+					format!("{name} x{count}")
+				} else {
+					name.clone()
+				}
+			})
+			.collect::<Vec<_>>()
+			.join(", ")
+	};
+
+	format!(
+		"Level {} ({}/{}), {items}",
+		user.level, user.this_levels_xp, user.xp_until_next_level
+	)
+}
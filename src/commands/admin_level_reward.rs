@@ -0,0 +1,60 @@
+use crate::{
+	data::state::DBEvent,
+	systems::{autoconfig::apply_config::update_config, level_rewards::backfill},
+	utils::{Admin, GetDB, Meta},
+	Context, Error,
+};
+use eyre::Result;
+
+/// Give a role to everyone that reaches a level, leave out the name to remove
+/// it
+#[poise::command(slash_command, guild_only)]
+pub async fn admin_level_reward(
+	ctx: Context<'_>,
+	#[description = "The level the role is given at"] level: u64,
+	#[description = "Like Wanderer, the role is called \"Level 10 — Wanderer\""] name: Option<
+		String,
+	>,
+) -> Result<(), Error> {
+	ctx.defer_ephemeral().await?;
+
+	if !ctx.author().is_admin() {
+		ctx.say("You are not an admin.").await?;
+		return Ok(());
+	}
+
+	let Some(server) = ctx.guild_id() else {
+		return Ok(());
+	};
+
+	let event = match &name {
+		Some(name) => DBEvent::LevelRewardAdd {
+			server,
+			level,
+			name: name.clone(),
+		},
+		None => DBEvent::LevelRewardForget { server, level },
+	};
+
+	ctx.db("admin level reward").await.add(event, ctx.meta())?;
+
+	// Makes, renames or deletes the role
+	update_config(&ctx, &server).await?;
+
+	if name.is_none() {
+		ctx.say(format!("Removed the reward for level {level}."))
+			.await?;
+		return Ok(());
+	}
+
+	let state = ctx.db("admin level reward").await.state().clone();
+
+	let granted = backfill(ctx.http(), &state, server, level).await;
+
+	ctx.say(format!(
+		"Everyone reaching level {level} now gets a role, {granted} people already had it coming."
+	))
+	.await?;
+
+	Ok(())
+}
@@ -0,0 +1,101 @@
+use crate::{
+	data::{leveling::LevelCurve, state::DBEvent},
+	utils::{Admin, GetDB, Meta},
+	Context, Error,
+};
+use eyre::Result;
+
+/// Change this server's leveling curves and recompute everyone's level
+#[poise::command(slash_command, guild_only)]
+pub async fn admin_leveling(
+	ctx: Context<'_>,
+	#[description = "Like `linear 100 10`, `polynomial 100 1 2 2`, `exponential 100 10`, `table 100 200`"]
+	curve: Option<String>,
+	#[description = "Distinct letters per step of message XP"] per_letters: Option<u64>,
+	#[description = "How steeply message XP grows with length"] exponent: Option<u32>,
+	#[description = "Message XP per step"] multiplier: Option<u64>,
+	#[description = "Most XP a single message can give"]
+	#[max = 1000]
+	cap: Option<u64>,
+	#[description = "XP for every active minute in voice"]
+	#[max = 100]
+	voice_per_minute: Option<u64>,
+) -> Result<(), Error> {
+	ctx.defer_ephemeral().await?;
+
+	if !ctx.author().is_admin() {
+		ctx.say("You are not an admin.").await?;
+		return Ok(());
+	}
+
+	let Some(server) = ctx.guild_id() else {
+		return Ok(());
+	};
+
+	let mut db = ctx.db("admin leveling").await;
+
+	let mut leveling = db.state().get_server_or_default(&server).leveling;
+
+	if let Some(curve) = curve {
+		leveling.curve = match curve.parse::<LevelCurve>() {
+			Ok(x) => x,
+			Err(err) => {
+				ctx.say(format!("Error: {err}")).await?;
+				return Ok(());
+			}
+		};
+	}
+
+	if let Some(per_letters) = per_letters {
+		leveling.message.per_letters = per_letters.max(1);
+	}
+
+	if let Some(exponent) = exponent {
+		leveling.message.exponent = exponent;
+	}
+
+	if let Some(multiplier) = multiplier {
+		leveling.message.multiplier = multiplier;
+	}
+
+	if let Some(cap) = cap {
+		leveling.message.cap = cap;
+	}
+
+	if let Some(voice_per_minute) = voice_per_minute {
+		leveling.voice_per_minute = voice_per_minute;
+	}
+
+	let global_profile = db.state().get_server_or_default(&server).global_profile;
+
+	db.add(
+		DBEvent::SetLeveling {
+			server,
+			leveling: leveling.clone(),
+		},
+		ctx.meta(),
+	)?;
+
+	let first_levels = (1..=5)
+		.map(|x| leveling.curve.required(x).to_string())
+		.collect::<Vec<_>>()
+		.join(", ");
+
+	ctx.say(format!(
+		"Curve: `{}` ({first_levels}, ...)\nMessages: ({{letters}} / {})^{} * {}, at most {} XP\nVoice: {} XP a minute{}",
+		leveling.curve,
+		leveling.message.per_letters,
+		leveling.message.exponent,
+		leveling.message.multiplier,
+		leveling.message.cap,
+		leveling.voice_per_minute,
+		if global_profile {
+			"\n-# This server uses the global profile, which keeps the default curves until it's turned off."
+		} else {
+			""
+		}
+	))
+	.await?;
+
+	Ok(())
+}
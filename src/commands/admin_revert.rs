@@ -0,0 +1,33 @@
+use crate::{
+	data::{state::DBEvent, RevertError},
+	utils::{Admin, GetDB, Meta},
+	Context, Error,
+};
+use eyre::Result;
+
+/// Cancel an event by its number in /admin_history
+#[poise::command(slash_command)]
+pub async fn admin_revert(ctx: Context<'_>, index: usize) -> Result<(), Error> {
+	ctx.defer_ephemeral().await?;
+
+	if !ctx.author().is_admin() {
+		ctx.say("You are not an admin.").await?;
+		return Ok(());
+	}
+
+	let mut db = ctx.db("admin revert").await;
+
+	match db.add(DBEvent::Revert { index }, ctx.meta()) {
+		Ok(_) => {
+			ctx.say(format!("Reverted event #{index}")).await?;
+		}
+		Err(err) => match err.downcast_ref::<RevertError>() {
+			Some(revert_error) => {
+				ctx.say(format!("Error: {revert_error}")).await?;
+			}
+			None => return Err(err.into()),
+		},
+	}
+
+	Ok(())
+}
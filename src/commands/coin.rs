@@ -1,39 +1,66 @@
 use crate::{
 	data::{
-		rng::Chance,
-		state::{DBEvent, SideChannel},
+		rng::{FairFlip, Flip, Seed},
+		state::DBEvent,
 	},
-	utils::GetDB,
+	utils::{GetDB, Meta},
 	Context, Error,
 };
 
 /// Flip a coin!
 #[poise::command(slash_command)]
-pub async fn coinflip(ctx: Context<'_>) -> eyre::Result<(), Error> {
+pub async fn coinflip(
+	ctx: Context<'_>,
+	#[description = "Mixed into the flip, so the bot can't pick the outcome"] client_seed: Option<
+		String,
+	>,
+) -> eyre::Result<(), Error> {
 	ctx.defer().await?;
 
 	let mut db = ctx.db("coin flip").await;
 
-	let chance = Chance::new();
-
-	let result = db.add(DBEvent::CoinFlip { chance })?;
-
-	match result {
-		SideChannel::CoinFlip { success } => {
-			if success {
-				ctx.say(format!(
-					"Heads! **{}** successful coin flips in a row! (that's a 1/{} chance)",
-					db.state().flips_in_a_row,
-					2u32.pow(db.state().flips_in_a_row)
-				))
-				.await?;
-			} else {
-				ctx.say("Unfortunately, you landed on tails.").await?;
-			}
-		}
-		event => {
-			panic!("CoinFlip event returned {:?} not CoinFlip", event)
-		}
+	// Use the seed we promised last time, see FairFlip for how to check it
+	let committed = db
+		.state()
+		.get_user_or_default(ctx.guild_id(), &ctx.author().id)
+		.next_flip_seed;
+
+	let flip = FairFlip {
+		seed: committed.unwrap_or_default(),
+		client_seed: client_seed.unwrap_or_else(|| ctx.id().to_string()),
+		next: Seed::new(),
+	};
+
+	let heads = flip.heads();
+	let proof = format!(
+		"-# Seed `{}` ({}), client seed `{}`, sha256 `{}`. Next seed commitment: `{}`",
+		flip.seed,
+		match committed {
+			Some(_) => format!("committed as `{}`", flip.seed.commitment()),
+			None => "uncommitted, this was your first flip".to_string(),
+		},
+		flip.client_seed,
+		flip.hash(),
+		flip.next.commitment()
+	);
+
+	db.add(
+		DBEvent::CoinFlip {
+			flip: Flip::Fair(flip),
+		},
+		ctx.meta(),
+	)?;
+
+	if heads {
+		ctx.say(format!(
+			"Heads! **{}** successful coin flips in a row! (that's a 1/{} chance)\n{proof}",
+			db.state().progress(ctx.guild_id()).flips_in_a_row,
+			2u32.pow(db.state().progress(ctx.guild_id()).flips_in_a_row)
+		))
+		.await?;
+	} else {
+		ctx.say(format!("Unfortunately, you landed on tails.\n{proof}"))
+			.await?;
 	}
 
 	Ok(())
@@ -1,20 +1,27 @@
-use crate::{data::state::DBEvent, utils::GetDB, Context, Error};
+use crate::{
+	data::state::DBEvent,
+	utils::{GetDB, Meta},
+	Context, Error,
+};
 
-/// Increments a global counter
+/// Increments this server's counter
 #[poise::command(slash_command)]
 pub async fn counter(ctx: Context<'_>) -> eyre::Result<(), Error> {
 	ctx.defer().await?;
 
 	let mut db = ctx.db("counter increment").await;
 
-	db.add(DBEvent::Counter {
-		user: ctx.author().id,
-	})?;
+	db.add(
+		DBEvent::Counter {
+			user: ctx.author().id,
+		},
+		ctx.meta(),
+	)?;
 
 	ctx.say(format!(
 		"This command has been run {} times, by {} different people!",
-		db.state().counter,
-		db.state().people_who_counted.len()
+		db.state().progress(ctx.guild_id()).counter,
+		db.state().progress(ctx.guild_id()).people_who_counted.len()
 	))
 	.await?;
 
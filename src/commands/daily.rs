@@ -0,0 +1,56 @@
+use crate::{
+	data::{
+		daily::{DailyReward, GRACE_DAYS},
+		rng::Seed,
+		state::{DBEvent, ReduceError},
+	},
+	utils::{GetDB, Meta},
+	Context, Error,
+};
+
+/// Claim your daily reward, it gets better the more days in a row you claim
+#[poise::command(slash_command)]
+pub async fn daily(ctx: Context<'_>) -> eyre::Result<(), Error> {
+	ctx.defer().await?;
+
+	let mut db = ctx.db("daily").await;
+
+	let seed = Seed::new();
+
+	if let Err(err) = db.add(
+		DBEvent::DailyClaim {
+			user: ctx.author().id,
+			seed,
+		},
+		ctx.meta(),
+	) {
+		return match err.downcast_ref::<ReduceError>() {
+			Some(ReduceError::AlreadyClaimedDaily) => {
+				ctx.say("You already claimed today's reward, come back tomorrow!")
+					.await?;
+				Ok(())
+			}
+			_ => Err(err.into()),
+		};
+	}
+
+	let streak = db
+		.state()
+		.get_user_or_default(ctx.guild_id(), &ctx.author().id)
+		.daily
+		.streak;
+
+	let reward = DailyReward::roll(streak, &seed);
+	let info = reward.item.info();
+
+	ctx.say(format!(
+		"You got **{} xp** and **{}** {}! You're on a **{streak}** day streak, you can skip \
+		 up to {GRACE_DAYS} day without losing it.\n-# Seed `{seed}`",
+		reward.xp,
+		info.name,
+		info.rarity.name(),
+	))
+	.await?;
+
+	Ok(())
+}
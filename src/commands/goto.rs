@@ -1,4 +1,9 @@
-use crate::{data::places::Place, systems::autoconfig::data::role, utils::GetDB, Context, Error};
+use crate::{
+	data::places::Place,
+	systems::autoconfig::{apply_config::update_config, data::role},
+	utils::GetDB,
+	Context, Error,
+};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -18,13 +23,15 @@ enum GotoError {
 pub async fn goto(ctx: Context<'_>, place: Place) -> eyre::Result<(), Error> {
 	ctx.defer_ephemeral().await?;
 
-	let mut db = ctx.db("counter increment").await;
+	let guild_id = ctx.guild_id().ok_or(GotoError::NotInAGuild)?;
 
-	db.update_config(&ctx, &ctx.guild_id().unwrap()).await?;
+	update_config(&ctx, &guild_id).await?;
 
-	let state = db.state();
-
-	let db_server = state.get_server_or_default(&ctx.guild_id().ok_or(GotoError::NotInAGuild)?);
+	let db_server = ctx
+		.db("goto")
+		.await
+		.state()
+		.get_server_or_default(&guild_id);
 
 	let role_name = &role(&format!("places/{}", place.id()));
 
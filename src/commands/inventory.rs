@@ -11,9 +11,10 @@ pub async fn inventory(ctx: Context<'_>, user: Option<User>) -> eyre::Result<(),
 	let db = ctx.db("inventory").await;
 
 	let db_user = if let Some(x) = &user {
-		db.state().get_user_or_default(&x.id)
+		db.state().get_user_or_default(ctx.guild_id(), &x.id)
 	} else {
-		db.state().get_user_or_default(&ctx.author().id)
+		db.state()
+			.get_user_or_default(ctx.guild_id(), &ctx.author().id)
 	};
 
 	if db_user.items.is_empty() {
@@ -0,0 +1,109 @@
+use crate::{
+	data::{
+		drawing::png_attachment,
+		leaderboard::{image, pages, rank, LeaderboardScope},
+	},
+	utils::GetDB,
+	Context, Error,
+};
+use poise::CreateReply;
+use serenity::all::{
+	ComponentInteractionCollector, CreateActionRow, CreateButton, CreateInteractionResponse,
+	EditAttachments, EditInteractionResponse, Timestamp,
+};
+use std::time::Duration;
+
+/// See who's furthest ahead
+#[poise::command(slash_command)]
+pub async fn leaderboard(
+	ctx: Context<'_>,
+	#[description = "Whose progress to rank, this server by default"] scope: Option<
+		LeaderboardScope,
+	>,
+) -> eyre::Result<(), Error> {
+	ctx.defer().await?;
+
+	let scope = scope.unwrap_or(LeaderboardScope::Guild);
+
+	let (ranked, season) = {
+		let db = ctx.db("leaderboard").await;
+
+		(
+			rank(db.state(), ctx.guild_id(), scope, &Timestamp::now()),
+			db.state().progress(ctx.guild_id()).season(),
+		)
+	};
+
+	let page_count = pages(ranked.len());
+	let mut page = 0;
+
+	let previous_id = format!("{}-previous", ctx.id());
+	let next_id = format!("{}-next", ctx.id());
+
+	let buttons = |page: usize| {
+		vec![CreateActionRow::Buttons(vec![
+			CreateButton::new(&previous_id)
+				.label("◀")
+				.disabled(page == 0),
+			CreateButton::new(&next_id)
+				.label("▶")
+				.disabled(page + 1 >= page_count),
+		])]
+	};
+
+	let render = |page: usize| {
+		let ranked = &ranked;
+
+		async move {
+			eyre::Ok(png_attachment(
+				&image(ctx, ranked, scope, season, page).await?,
+				"leaderboard.png",
+			)?)
+		}
+	};
+
+	let reply = ctx
+		.send(
+			CreateReply::default()
+				.attachment(render(page).await?)
+				.components(buttons(page)),
+		)
+		.await?;
+
+	let ctx_id = ctx.id();
+	let author = ctx.author().id;
+
+	while let Some(press) = ComponentInteractionCollector::new(ctx)
+		.filter(move |x| x.data.custom_id.starts_with(&ctx_id.to_string()) && x.user.id == author)
+		.timeout(Duration::from_secs(120))
+		.await
+	{
+		if press.data.custom_id == next_id {
+			page = (page + 1).min(page_count - 1);
+		} else if press.data.custom_id == previous_id {
+			page = page.saturating_sub(1);
+		}
+
+		// Drawing takes longer than discord waits for an answer
+		press
+			.create_response(ctx, CreateInteractionResponse::Acknowledge)
+			.await?;
+
+		press
+			.edit_response(
+				ctx,
+				EditInteractionResponse::new()
+					.attachments(EditAttachments::new().add(render(page).await?))
+					.components(buttons(page)),
+			)
+			.await?;
+	}
+
+	// Nobody's turning pages anymore
+	reply
+		.edit(ctx, CreateReply::default().components(vec![]))
+		.await
+		.ok();
+
+	Ok(())
+}
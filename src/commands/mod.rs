@@ -1,8 +1,22 @@
+pub mod admin_announcements;
+pub mod admin_anti_spam;
 pub mod admin_burn;
+pub mod admin_compact;
+pub mod admin_end_season;
 pub mod admin_give;
+pub mod admin_global_profile;
+pub mod admin_history;
+pub mod admin_level_reward;
+pub mod admin_leveling;
+pub mod admin_revert;
 pub mod coin;
 pub mod counter;
+pub mod daily;
 pub mod goto;
 pub mod inventory;
+pub mod leaderboard;
+pub mod pings;
+pub mod rep;
+pub mod roll;
 pub mod status;
 pub mod test;
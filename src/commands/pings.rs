@@ -0,0 +1,28 @@
+use crate::{
+	data::state::DBEvent,
+	utils::{GetDB, Meta},
+	Context, Error,
+};
+
+/// Choose whether you get pinged when you level up
+#[poise::command(slash_command)]
+pub async fn pings(ctx: Context<'_>, enabled: bool) -> eyre::Result<(), Error> {
+	ctx.defer_ephemeral().await?;
+
+	ctx.db("pings").await.add(
+		DBEvent::SetLevelUpPings {
+			user: ctx.author().id,
+			enabled,
+		},
+		ctx.meta(),
+	)?;
+
+	ctx.say(if enabled {
+		"You'll be pinged when you level up."
+	} else {
+		"Your level-ups won't ping you anymore."
+	})
+	.await?;
+
+	Ok(())
+}
@@ -0,0 +1,57 @@
+use crate::{
+	data::{
+		reputation::RepSource,
+		state::{DBEvent, ReduceError},
+	},
+	utils::{GetDB, Meta},
+	Context, Error,
+};
+use serenity::all::User;
+
+/// Thank someone for their help
+#[poise::command(slash_command)]
+pub async fn rep(
+	ctx: Context<'_>,
+	#[description = "Who to thank"] user: User,
+) -> eyre::Result<(), Error> {
+	ctx.defer_ephemeral().await?;
+
+	if user.bot {
+		ctx.say("Bots don't collect reputation.").await?;
+		return Ok(());
+	}
+
+	let mut db = ctx.db("rep").await;
+
+	let result = db.add(
+		DBEvent::GiveReputation {
+			giver: ctx.author().id,
+			receiver: user.id,
+			source: RepSource::Command,
+		},
+		ctx.meta(),
+	);
+
+	match result {
+		Ok(_) => {
+			let reputation = db
+				.state()
+				.get_user_or_default(ctx.guild_id(), &user.id)
+				.reputation;
+
+			ctx.say(format!(
+				"Thanked <@{}>, they have **{reputation}** rep now.",
+				user.id
+			))
+			.await?;
+		}
+		Err(err) => match err.downcast_ref::<ReduceError>() {
+			Some(ReduceError::Reputation(rep_error)) => {
+				ctx.say(format!("Sorry, {rep_error}.")).await?;
+			}
+			_ => return Err(err.into()),
+		},
+	}
+
+	Ok(())
+}
@@ -0,0 +1,66 @@
+use crate::{
+	data::{
+		rng::{Dice, Seed},
+		state::DBEvent,
+	},
+	utils::{GetDB, Meta},
+	Context, Error,
+};
+
+/// Roll some dice, like 2d6+3
+#[poise::command(slash_command)]
+pub async fn roll(ctx: Context<'_>, dice: String) -> eyre::Result<(), Error> {
+	let dice = match dice.parse::<Dice>() {
+		Ok(x) => x,
+		Err(err) => {
+			ctx.say(format!("Error: {err}")).await?;
+			return Ok(());
+		}
+	};
+
+	let mut db = ctx.db("dice roll").await;
+
+	// Like coin flips, every roll uses the seed the last one committed to
+	let committed = db
+		.state()
+		.get_user_or_default(ctx.guild_id(), &ctx.author().id)
+		.next_roll_seed;
+
+	let seed = committed.unwrap_or_default();
+	let next = Seed::new();
+
+	db.add(
+		DBEvent::DiceRoll {
+			user: ctx.author().id,
+			dice,
+			seed,
+			next,
+		},
+		ctx.meta(),
+	)?;
+
+	drop(db);
+
+	let roll = dice.roll(&mut seed.rng());
+
+	let modifier = match dice.modifier {
+		0 => String::new(),
+		x if x > 0 => format!(" + {x}"),
+		x => format!(" - {}", -x),
+	};
+
+	let committed = match committed {
+		Some(_) => format!("committed as `{}`", seed.commitment()),
+		None => "uncommitted, this was your first roll".to_string(),
+	};
+
+	ctx.say(format!(
+		"🎲 {:?}{modifier} = **{}**\n-# Seed `{seed}` ({committed}). Next seed commitment: `{}`",
+		roll.rolls,
+		roll.total,
+		next.commitment()
+	))
+	.await?;
+
+	Ok(())
+}
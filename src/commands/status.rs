@@ -13,11 +13,16 @@ pub async fn status(ctx: Context<'_>, person: Option<User>) -> eyre::Result<(),
 		ctx.author().clone()
 	};
 
-	let db = ctx.db("status").await;
+	let (db_user, season) = {
+		let db = ctx.db("status").await;
 
-	let db_user = db.state().get_user_or_default(&user.id);
+		(
+			db.state().get_user_or_default(ctx.guild_id(), &user.id),
+			db.state().progress(ctx.guild_id()).season(),
+		)
+	};
 
-	ctx.send(CreateReply::default().attachment(db_user.attachment_image(&user).await?))
+	ctx.send(CreateReply::default().attachment(db_user.attachment_image(&user, season).await?))
 		.await?;
 
 	Ok(())
@@ -1,5 +1,5 @@
 use crate::{
-	systems::autoconfig::data::ServerConfigRoleId,
+	systems::autoconfig::{apply_config::update_config, data::ServerConfigRoleId},
 	utils::{Admin, GetDB},
 	Context, Error,
 };
@@ -14,19 +14,14 @@ pub async fn test(ctx: Context<'_>) -> Result<(), Error> {
 		return Ok(());
 	}
 
-	let mut db = ctx.db("test").await;
+	update_config(&ctx, &ctx.guild_id().unwrap()).await?;
 
-	db.update_config(&ctx, &ctx.guild_id().unwrap()).await?;
+	let admin_role = ctx.db("test").await.state().servers[&ctx.guild_id().unwrap()].roles
+		[&ServerConfigRoleId("admin".to_string())];
 
 	let member = ctx.author_member().await.unwrap();
 
-	member
-		.add_role(
-			ctx,
-			db.state().servers[&ctx.guild_id().unwrap()].roles
-				[&ServerConfigRoleId("admin".to_string())],
-		)
-		.await?;
+	member.add_role(ctx, admin_role).await?;
 
 	ctx.say("did the thing").await?;
 
@@ -1,3 +1,4 @@
+use crate::data::storage::StorageBackend;
 use serenity::all::{GuildId, UserId};
 
 pub fn get_testing_guild() -> GuildId { GuildId::new(1253105126600867921) }
@@ -7,3 +8,27 @@ pub fn get_token() -> &'static str {
 }
 
 pub fn get_bot_id() -> UserId { UserId::new(1253145465461932063) }
+
+/// The guild events from before progress was kept per guild are assigned to
+pub fn get_legacy_guild() -> GuildId { get_testing_guild() }
+
+/// How many events can be added before the database stores a new snapshot
+pub fn get_snapshot_interval() -> usize { 500 }
+
+/// How many backups of the database to keep, set `QUICKSILVER_BACKUPS` to
+/// change it
+pub fn get_backup_count() -> usize {
+	std::env::var("QUICKSILVER_BACKUPS")
+		.ok()
+		.and_then(|x| x.parse().ok())
+		.unwrap_or(5)
+}
+
+/// Where the database is kept, set `QUICKSILVER_STORAGE=sqlite` to use SQLite
+/// instead of the journal file
+pub fn get_storage_backend() -> StorageBackend {
+	match std::env::var("QUICKSILVER_STORAGE").as_deref() {
+		Ok("sqlite") => StorageBackend::Sqlite,
+		_ => StorageBackend::Journal,
+	}
+}
@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+use serenity::all::UserId;
+
+/// Where a guild's level-ups are announced
+#[derive(
+	Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, poise::ChoiceParameter,
+)]
+pub enum AnnouncementMode {
+	/// As a reply to the message the user levelled up with, or just in its
+	/// channel when there wasn't one
+	#[default]
+	#[name = "Where they levelled up"]
+	Reply,
+
+	/// In the level-ups channel autoconfig makes
+	#[name = "A level-ups channel"]
+	Channel,
+
+	#[name = "In their DMs"]
+	Dm,
+
+	#[name = "Nowhere"]
+	Silent,
+}
+
+/// How a guild announces level-ups
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AnnouncementConfig {
+	pub mode: AnnouncementMode,
+
+	/// The message, see [`AnnouncementConfig::render`] for what can go in it
+	pub template: String,
+
+	/// Attach the user's profile card
+	pub card: bool,
+}
+
+pub const DEFAULT_TEMPLATE: &str =
+	"{user} ⬆️ Level up from {previous} to **{level}**. {xp} xp until next level";
+
+impl Default for AnnouncementConfig {
+	fn default() -> Self {
+		Self {
+			mode: AnnouncementMode::default(),
+			template: DEFAULT_TEMPLATE.to_string(),
+			card: false,
+		}
+	}
+}
+
+impl AnnouncementConfig {
+	/// Fills in `{user}`, `{previous}`, `{level}` and `{xp}`, the XP still
+	/// needed for the next level
+	pub fn render(&self, user: UserId, previous: u64, level: u64, xp: u64) -> String {
+		self.template
+			.replace("{user}", &format!("<@{user}>"))
+			.replace("{previous}", &previous.to_string())
+			.replace("{level}", &level.to_string())
+			.replace("{xp}", &xp.to_string())
+	}
+}
@@ -0,0 +1,109 @@
+use crate::data::envelope::EventMeta;
+use serde::{Deserialize, Serialize};
+use serenity::all::ChannelId;
+use std::collections::{HashMap, VecDeque};
+
+const HOUR: i64 = 60 * 60;
+
+/// How a guild limits XP from messages
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AntiSpamConfig {
+	/// Seconds a user has to wait after gaining XP before they can gain more
+	pub cooldown: u64,
+
+	/// Keep a separate cooldown for every channel instead of one for the guild
+	pub per_channel: bool,
+
+	/// The most XP a user can gain in any hour
+	pub hourly_cap: Option<u64>,
+
+	/// Messages whose [`crate::utils::Fingerprint`] differs from the user's
+	/// last one in at most this many bits give no XP
+	pub duplicate_distance: Option<u32>,
+}
+
+impl Default for AntiSpamConfig {
+	fn default() -> Self {
+		Self {
+			cooldown: 60,
+			per_channel: false,
+			hourly_cap: Some(300),
+			duplicate_distance: Some(6),
+		}
+	}
+}
+
+/// What a user recently did to gain XP, timestamps are in unix seconds
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct XpActivity {
+	pub last_gain: Option<i64>,
+	pub channel_gains: HashMap<ChannelId, i64>,
+
+	/// XP gained in the last hour, oldest first
+	pub recent: VecDeque<(i64, u64)>,
+
+	pub last_fingerprint: Option<u64>,
+}
+
+impl XpActivity {
+	/// How much of `xp` a message sent at `meta` is allowed to give, and
+	/// remembers it
+	pub fn grant(
+		&mut self,
+		config: &AntiSpamConfig,
+		meta: &EventMeta,
+		fingerprint: Option<u64>,
+		xp: u64,
+	) -> u64 {
+		let now = meta.timestamp.unix_timestamp();
+
+		let duplicate = match (
+			config.duplicate_distance,
+			fingerprint,
+			self.last_fingerprint,
+		) {
+			(Some(distance), Some(new), Some(old)) => (new ^ old).count_ones() <= distance,
+			_ => false,
+		};
+
+		if fingerprint.is_some() {
+			self.last_fingerprint = fingerprint;
+		}
+
+		if duplicate {
+			return 0;
+		}
+
+		let channel = meta.channel.filter(|_| config.per_channel);
+
+		let last_gain = match channel {
+			Some(channel) => self.channel_gains.get(&channel).copied(),
+			None => self.last_gain,
+		};
+
+		if last_gain.is_some_and(|x| now - x < config.cooldown as i64) {
+			return 0;
+		}
+
+		self.recent.retain(|(at, _)| now - at < HOUR);
+
+		let xp = match config.hourly_cap {
+			Some(cap) => xp.min(cap.saturating_sub(self.recent.iter().map(|x| x.1).sum())),
+			None => xp,
+		};
+
+		if xp == 0 {
+			return 0;
+		}
+
+		self.last_gain = Some(now);
+
+		if let Some(channel) = meta.channel {
+			self.channel_gains.insert(channel, now);
+		}
+
+		self.recent.push_back((now, xp));
+
+		xp
+	}
+}
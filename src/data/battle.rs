@@ -1,6 +1,7 @@
 use eyre::Result;
+use serde::{Deserialize, Serialize};
 use std::{
-	cmp::{max, min},
+	cmp::min,
 	fmt::{Debug, Formatter},
 };
 use thiserror::Error;
@@ -51,7 +52,7 @@ impl LivingBuilder {
 	}
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Living {
 	health: u32,
 	max_health: u32,
@@ -65,10 +66,10 @@ impl Debug for Living {
 
 impl Living {
 	pub fn heal(&mut self, amount: u32) {
-		self.health = min(self.max_health, self.health + amount);
+		self.health = min(self.max_health, self.health.saturating_add(amount));
 	}
 
-	pub fn damage(&mut self, amount: u32) { self.health = max(0, self.health - amount); }
+	pub fn damage(&mut self, amount: u32) { self.health = self.health.saturating_sub(amount); }
 
 	pub fn health(&self) -> u32 { self.health }
 
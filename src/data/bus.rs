@@ -0,0 +1,20 @@
+use crate::data::{envelope::EventEnvelope, state::DBState};
+use std::fmt::Debug;
+
+/// An event that was just added to the timeline, with the state on either side
+/// of it
+pub struct Committed<'a> {
+	pub index: usize,
+	pub envelope: &'a EventEnvelope,
+	pub before: &'a DBState,
+	pub after: &'a DBState,
+}
+
+/// Reacts to events once they're committed, see
+/// [`crate::data::Database::subscribe`]
+///
+/// Subscribers are called while the database is locked, so anything slow, like
+/// talking to Discord, should be spawned as its own task.
+pub trait Subscriber: Debug + Send {
+	fn committed(&self, event: &Committed);
+}
@@ -0,0 +1,108 @@
+use crate::data::{
+	items::{InventoryItem, Rarity},
+	rng::Seed,
+};
+use serde::{Deserialize, Serialize};
+use serenity::all::Timestamp;
+
+const DAY: i64 = 24 * 60 * 60;
+
+/// Days a streak survives without a claim, so missing one day doesn't lose it
+pub const GRACE_DAYS: i64 = 1;
+
+/// Streaks stop making rewards better after this many days
+pub const MAX_BONUS_STREAK: u64 = 14;
+
+const BASE_XP: u64 = 50;
+const XP_PER_STREAK_DAY: u64 = 10;
+
+/// Items `/daily` can give, the testing gizmo isn't one of them
+const DROPS: [InventoryItem; 8] = [
+	InventoryItem::Stick,
+	InventoryItem::Rock,
+	InventoryItem::Gun,
+	InventoryItem::Wand,
+	InventoryItem::Ace,
+	InventoryItem::ScytheVivi,
+	InventoryItem::CrossMinsley,
+	InventoryItem::OracleAmulet,
+];
+
+/// Days since the unix epoch, days start at midnight UTC
+pub fn day(at: &Timestamp) -> i64 { at.unix_timestamp().div_euclid(DAY) }
+
+/// A user's run of daily claims
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DailyStreak {
+	/// Days claimed in a row, counting grace days as kept
+	pub streak: u64,
+
+	/// The [`day`] of the last claim
+	pub last_claim: Option<i64>,
+}
+
+impl DailyStreak {
+	/// Whether a claim at `at` would be the second one that day
+	pub fn claimed_on(&self, at: &Timestamp) -> bool { self.last_claim == Some(day(at)) }
+
+	/// The streak as of `at`, zero if it has lapsed
+	pub fn current(&self, at: &Timestamp) -> u64 {
+		match self.last_claim {
+			Some(last) if day(at) - last <= 1 + GRACE_DAYS => self.streak,
+			_ => 0,
+		}
+	}
+
+	/// Records a claim at `at` and gives back the new streak, the caller has
+	/// to check [`DailyStreak::claimed_on`] first
+	pub fn claim(&mut self, at: &Timestamp) -> u64 {
+		self.streak = self.current(at) + 1;
+		self.last_claim = Some(day(at));
+		self.streak
+	}
+}
+
+/// What a claim gives
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DailyReward {
+	pub xp: u64,
+	pub item: InventoryItem,
+}
+
+impl DailyReward {
+	/// The reward for reaching `streak`, the item rolled from `seed`
+	pub fn roll(streak: u64, seed: &Seed) -> Self {
+		let table = drop_table(streak);
+
+		Self {
+			xp: BASE_XP + XP_PER_STREAK_DAY * bonus_days(streak),
+			item: *seed
+				.rng()
+				.choose_weighted(&table)
+				.expect("every drop has some weight"),
+		}
+	}
+}
+
+fn bonus_days(streak: u64) -> u64 { streak.clamp(1, MAX_BONUS_STREAK) - 1 }
+
+/// How likely every item is at `streak`, rarer items get likelier the longer
+/// the streak
+pub fn drop_table(streak: u64) -> Vec<(InventoryItem, u64)> {
+	let days = bonus_days(streak);
+
+	DROPS
+		.iter()
+		.map(|item| {
+			let weight = match item.info().rarity {
+				Rarity::Common => 600,
+				Rarity::Uncommon => 250 + 10 * days,
+				Rarity::Rare => 100 + 10 * days,
+				Rarity::Mythical => 20 + 5 * days,
+				Rarity::Unique => 5 + 2 * days,
+			};
+
+			(*item, weight)
+		})
+		.collect()
+}
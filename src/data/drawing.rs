@@ -0,0 +1,110 @@
+use crate::data::user::DBUserError;
+use ab_glyph::FontVec;
+use eyre::Result;
+use image::{
+	imageops::{overlay, FilterType},
+	DynamicImage, GenericImage, Rgba, RgbaImage,
+};
+use imageproc::{drawing::draw_filled_rect_mut, rect::Rect};
+use serenity::all::{CreateAttachment, User};
+use std::io::Cursor;
+
+/// Shared by every card the bot draws
+pub const BACKGROUND: Rgba<u8> = Rgba([17, 17, 17, 255]);
+pub const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+macro_rules! rect {
+	($pos: expr, $size: expr) => {
+		Rect::at($pos.0 as i32, $pos.1 as i32).of_size($size.0 as u32, $size.1 as u32)
+	};
+}
+
+pub(crate) use rect;
+
+/// An empty card of `size`
+pub fn canvas(size: (u32, u32)) -> RgbaImage { RgbaImage::from_pixel(size.0, size.1, BACKGROUND) }
+
+pub fn light_font() -> Result<FontVec> {
+	let bytes = std::fs::read("./fonts/light.ttf")?;
+
+	Ok(FontVec::try_from_vec(bytes).map_err(|_| DBUserError::FontFailedToParse)?)
+}
+
+/// The name people see for `user`, in capitals
+pub fn display_name(user: &User) -> String {
+	user.global_name
+		.as_ref()
+		.unwrap_or(&user.name)
+		.to_uppercase()
+}
+
+/// An outlined bar, `filled` from 0 to 1, always showing at least a sliver
+pub fn draw_bar(img: &mut RgbaImage, pos: (f32, f32), size: (f32, f32), filled: f32) {
+	let filled_size = (
+		if size.0 * filled > 1f32 {
+			size.0 * filled
+		} else {
+			1f32
+		},
+		size.1,
+	);
+
+	// Fill the outline
+	draw_filled_rect_mut(img, rect!(pos, size), WHITE);
+
+	// Hollow out the outline
+	let outline_thickness = 1f32;
+	let outline_hole_pos = (pos.0 + outline_thickness, pos.1 + outline_thickness);
+	let outline_hole_size = (
+		size.0 - 2f32 * outline_thickness,
+		size.1 - 2f32 * outline_thickness,
+	);
+
+	draw_filled_rect_mut(img, rect!(outline_hole_pos, outline_hole_size), BACKGROUND);
+
+	// Draw the filled portion
+	draw_filled_rect_mut(img, rect!(pos, filled_size), WHITE);
+}
+
+/// Draws `user`'s profile picture as a circle of `size` at `pos`
+pub async fn draw_avatar(
+	img: &mut RgbaImage,
+	user: &User,
+	pos: (i64, i64),
+	size: f32,
+) -> Result<()> {
+	let avatar_url = user.avatar_url().unwrap_or(user.default_avatar_url());
+	let avatar_bytes = reqwest::get(avatar_url).await?.bytes().await?;
+
+	let avatar_image = image::load_from_memory(&avatar_bytes)?;
+
+	let mut tiny = avatar_image.resize(size as u32, size as u32, FilterType::CatmullRom);
+
+	clip_circle(&mut tiny, size);
+
+	overlay(img, &tiny, pos.0, pos.1);
+
+	Ok(())
+}
+
+fn clip_circle(img: &mut DynamicImage, size: f32) {
+	for x in 0..img.width() {
+		for y in 0..img.height() {
+			let pos = (x as f32 / size, y as f32 / size);
+			let distance = (1f32 - (pos.0 * 2f32)).powf(2f32) + (1f32 - (pos.1 * 2f32)).powf(2f32);
+
+			if distance > 1f32 {
+				img.put_pixel(x, y, BACKGROUND);
+			}
+		}
+	}
+}
+
+/// Encodes `img` as a PNG attachment, without going through the disk
+pub fn png_attachment(img: &RgbaImage, name: &str) -> Result<CreateAttachment> {
+	let mut bytes = Cursor::new(vec![]);
+
+	img.write_to(&mut bytes, image::ImageFormat::Png)?;
+
+	Ok(CreateAttachment::bytes(bytes.into_inner(), name))
+}
@@ -0,0 +1,118 @@
+use crate::data::state::DBEvent;
+use serde::{Deserialize, Serialize};
+use serenity::all::{ChannelId, GuildId, MessageId, Timestamp, UserId};
+
+/// Where an event came from
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum EventSource {
+	/// A slash command, by name
+	Command(String),
+
+	/// Something the bot did by itself, like handing out XP
+	System(String),
+
+	/// Recorded before events had sources
+	Unknown,
+}
+
+/// Who, when and where an event happened
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EventMeta {
+	/// When the event was added, events recorded before timestamps existed
+	/// are dated to the unix epoch
+	pub timestamp: Timestamp,
+
+	/// The user that caused this event
+	pub actor: Option<UserId>,
+
+	/// The guild this event happened in
+	pub guild: Option<GuildId>,
+
+	/// The channel this event happened in
+	pub channel: Option<ChannelId>,
+
+	/// The message that caused this event, in `channel`
+	pub message: Option<MessageId>,
+
+	pub source: EventSource,
+}
+
+impl EventMeta {
+	pub fn now(source: EventSource, actor: Option<UserId>, guild: Option<GuildId>) -> Self {
+		Self {
+			timestamp: Timestamp::now(),
+			actor,
+			guild,
+			channel: None,
+			message: None,
+			source,
+		}
+	}
+
+	pub fn in_channel(mut self, channel: ChannelId) -> Self {
+		self.channel = Some(channel);
+		self
+	}
+
+	/// Dates the event to `timestamp` instead of now
+	pub fn at(mut self, timestamp: Timestamp) -> Self {
+		self.timestamp = timestamp;
+		self
+	}
+
+	pub fn from_message(mut self, channel: ChannelId, message: MessageId) -> Self {
+		self.channel = Some(channel);
+		self.message = Some(message);
+		self
+	}
+
+	/// Metadata for events from before it was recorded
+	pub fn unknown() -> Self {
+		Self {
+			timestamp: Timestamp::from_unix_timestamp(0).unwrap(),
+			actor: None,
+			guild: None,
+			channel: None,
+			message: None,
+			source: EventSource::Unknown,
+		}
+	}
+}
+
+/// An event along with its [`EventMeta`], this is what the timeline is made of
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EventEnvelope {
+	pub meta: EventMeta,
+	pub event: DBEvent,
+}
+
+impl EventEnvelope {
+	/// The user whose progress this event is about
+	pub fn subject(&self) -> Option<UserId> {
+		match &self.event {
+			DBEvent::Counter { user }
+			| DBEvent::UserSendMessage { user, .. }
+			| DBEvent::VoiceSession { user, .. }
+			| DBEvent::SetLevelUpPings { user, .. }
+			| DBEvent::DailyClaim { user, .. }
+			| DBEvent::DiceRoll { user, .. }
+			| DBEvent::AdminGive { user, .. }
+			| DBEvent::AdminBurn { user, .. } => Some(*user),
+			DBEvent::GiveReputation { receiver, .. } => Some(*receiver),
+			DBEvent::CoinFlip { .. } => self.meta.actor,
+			DBEvent::ChannelForget { .. }
+			| DBEvent::ChannelAdd { .. }
+			| DBEvent::RoleForget { .. }
+			| DBEvent::RoleAdd { .. }
+			| DBEvent::EndSeason { .. }
+			| DBEvent::LevelRewardAdd { .. }
+			| DBEvent::LevelRewardForget { .. }
+			| DBEvent::SetLeveling { .. }
+			| DBEvent::SetAnnouncements { .. }
+			| DBEvent::SetAntiSpam { .. }
+			| DBEvent::SetGlobalProfile { .. }
+			| DBEvent::Import { .. }
+			| DBEvent::Revert { .. } => None,
+		}
+	}
+}
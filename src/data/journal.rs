@@ -0,0 +1,166 @@
+use crate::data::{envelope::EventEnvelope, schema, storage::write_atomic};
+use eyre::{Result, WrapErr};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+	fs::{File, OpenOptions},
+	io::{BufRead, BufReader, Write},
+	path::{Path, PathBuf},
+};
+
+/// The first line of a journal that was compacted, so a snapshot that doesn't
+/// fit it can be told apart from one that does
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct Header {
+	/// How many events were archived before the first one in this journal
+	starts_after: usize,
+}
+
+/// An append-only file with one JSON encoded [`EventEnvelope`] per line
+#[derive(Debug)]
+pub struct Journal {
+	path: PathBuf,
+	file: File,
+
+	/// Opened with [`Journal::open_read_only`], so a torn last line is left
+	/// alone instead of cut off
+	read_only: bool,
+}
+
+impl Journal {
+	/// Opens the journal at `path`, creating it if it doesn't exist
+	pub fn open(path: &Path) -> Result<Self> {
+		let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+		Ok(Self {
+			path: path.to_path_buf(),
+			file,
+			read_only: false,
+		})
+	}
+
+	/// Opens the journal at `path` without ever writing to it, it has to exist
+	pub fn open_read_only(path: &Path) -> Result<Self> {
+		Ok(Self {
+			path: path.to_path_buf(),
+			file: File::open(path)?,
+			read_only: true,
+		})
+	}
+
+	pub fn path(&self) -> &Path { &self.path }
+
+	/// Reads every event in the journal, see [`read`]
+	pub fn read(&self) -> Result<Vec<EventEnvelope>> { read(&self.path, !self.read_only) }
+
+	/// How many events were archived before this journal, `None` if it was
+	/// never compacted or was compacted before journals said so
+	pub fn starts_after(&self) -> Result<Option<usize>> {
+		let mut line = String::new();
+
+		BufReader::new(File::open(&self.path)?).read_line(&mut line)?;
+
+		Ok(header(line.as_bytes()).map(|x| x.starts_after))
+	}
+
+	/// Writes a single event and waits for it to hit the disk
+	pub fn append(&mut self, event: &EventEnvelope) -> Result<()> {
+		let mut line = schema::encode(event)?;
+		line.push('\n');
+
+		self.file.write_all(line.as_bytes())?;
+		self.file.sync_data()?;
+
+		Ok(())
+	}
+
+	/// Replaces the whole journal with `events`, which come after the first
+	/// `starts_after` events of the timeline
+	pub fn rewrite(&mut self, starts_after: usize, events: &[EventEnvelope]) -> Result<()> {
+		let mut content = serde_json::to_string(&Header { starts_after })?;
+		content.push('\n');
+		content += &encode(events)?;
+
+		write_atomic(&self.path, content.as_bytes())?;
+
+		self.file = OpenOptions::new().append(true).open(&self.path)?;
+
+		Ok(())
+	}
+}
+
+/// Writes `events` to a new journal file at `path`, replacing it atomically if
+/// it already exists
+pub fn write(path: &Path, events: &[EventEnvelope]) -> Result<()> {
+	write_atomic(path, encode(events)?.as_bytes())
+}
+
+fn encode(events: &[EventEnvelope]) -> Result<String> {
+	let mut content = String::new();
+
+	for event in events {
+		content += &schema::encode(event)?;
+		content.push('\n');
+	}
+
+	Ok(content)
+}
+
+fn header(line: &[u8]) -> Option<Header> { serde_json::from_slice(line.trim_ascii()).ok() }
+
+/// Reads every event in a journal file, skipping its [`Header`]
+///
+/// If the process died halfway through an append, the last line will be cut
+/// off. That line is dropped and, if `repair` is set, the file is truncated
+/// back to the last complete event, any other malformed line is an error.
+pub fn read(path: &Path, repair: bool) -> Result<Vec<EventEnvelope>> {
+	let content = std::fs::read(path)?;
+
+	let mut events = vec![];
+	let mut valid_length = 0;
+
+	for (idx, line) in content.split_inclusive(|x| *x == b'\n').enumerate() {
+		if line.trim_ascii().is_empty() || (idx == 0 && header(line).is_some()) {
+			valid_length += line.len();
+			continue;
+		}
+
+		if !line.ends_with(b"\n") {
+			println!(
+				"warn: dropping truncated event at the end of {}",
+				path.display()
+			);
+			break;
+		}
+
+		let event = schema::decode(line).wrap_err_with(|| {
+			format!("malformed event on line {} of {}", idx + 1, path.display())
+		})?;
+
+		events.push(event);
+		valid_length += line.len();
+	}
+
+	if repair && valid_length != content.len() {
+		OpenOptions::new()
+			.write(true)
+			.open(path)?
+			.set_len(valid_length as u64)?;
+	}
+
+	Ok(events)
+}
+
+/// Converts a JSON array timeline (the old `db.json` format) into a journal
+pub fn migrate_from_json(legacy: &Path, journal: &Path) -> Result<()> {
+	let file_content = std::fs::read_to_string(legacy)?;
+	let events = serde_json::from_str::<Vec<Value>>(&file_content)?
+		.into_iter()
+		.map(schema::decode_value)
+		.collect::<Result<Vec<_>>>()?;
+
+	// The journal only shows up once it's complete, so a crash here just means
+	// we migrate again next time
+	write(journal, &events)
+}
@@ -0,0 +1,258 @@
+use crate::data::{
+	drawing::{canvas, display_name, draw_avatar, draw_bar, light_font, WHITE},
+	state::DBState,
+	user::{week, DBUser},
+};
+use ab_glyph::PxScale;
+use eyre::Result;
+use image::RgbaImage;
+use imageproc::drawing::draw_text_mut;
+use serenity::all::{CacheHttp, GuildId, Timestamp, UserId};
+use std::{cmp::Reverse, collections::BTreeMap, iter::once};
+
+/// How many players fit on one page of the leaderboard
+pub const PAGE_SIZE: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum LeaderboardScope {
+	#[name = "This server"]
+	Guild,
+
+	#[name = "Everywhere"]
+	Global,
+
+	#[name = "This server, this week"]
+	Week,
+
+	#[name = "This server, all seasons"]
+	Lifetime,
+
+	#[name = "This server, reputation"]
+	Reputation,
+}
+
+impl LeaderboardScope {
+	/// The heading of the leaderboard, `season` being the one that's running
+	pub fn title(&self, season: usize) -> String {
+		match self {
+			LeaderboardScope::Guild => format!("SEASON {season}"),
+			LeaderboardScope::Global => "EVERYWHERE".to_string(),
+			LeaderboardScope::Week => "THIS WEEK".to_string(),
+			LeaderboardScope::Lifetime => "ALL SEASONS".to_string(),
+			LeaderboardScope::Reputation => "MOST THANKED".to_string(),
+		}
+	}
+}
+
+/// Everyone with progress in `scope`, best first
+///
+/// The season scope goes by level and then XP, the weekly one by XP gained
+/// since monday, the lifetime one by all XP ever gained and the reputation
+/// one by how often people were thanked. The global one adds up everyone's
+/// lifetime XP from every guild and the global profile.
+pub fn rank(
+	state: &DBState,
+	guild: Option<GuildId>,
+	scope: LeaderboardScope,
+	now: &Timestamp,
+) -> Vec<(UserId, DBUser)> {
+	let mut users = match scope {
+		LeaderboardScope::Global => everywhere(state),
+		_ => state.progress(guild).standings(),
+	};
+
+	match scope {
+		LeaderboardScope::Week => {
+			let this_week = week(now);
+
+			users.retain(|(_, x)| x.week == this_week && x.weekly_xp > 0);
+			users.sort_by_key(|(id, x)| (Reverse(x.weekly_xp), *id));
+		}
+		LeaderboardScope::Lifetime | LeaderboardScope::Global => {
+			users.retain(|(_, x)| x.total_xp > 0);
+			users.sort_by_key(|(id, x)| (Reverse(x.total_xp), *id));
+		}
+		LeaderboardScope::Reputation => {
+			users.retain(|(_, x)| x.reputation > 0);
+			users.sort_by_key(|(id, x)| (Reverse(x.reputation), *id));
+		}
+		LeaderboardScope::Guild => {}
+	}
+
+	users
+}
+
+/// Every user's progress in every guild and the global profile, added up
+fn everywhere(state: &DBState) -> Vec<(UserId, DBUser)> {
+	let progresses = once(&state.global).chain(
+		state
+			.servers
+			.values()
+			.filter(|x| !x.global_profile)
+			.map(|x| &x.progress),
+	);
+
+	let mut merged = BTreeMap::<UserId, DBUser>::new();
+
+	for progress in progresses {
+		for (id, user) in &progress.users {
+			let merged = merged.entry(*id).or_default();
+
+			merged.total_xp += user.total_xp;
+			merged.reputation += user.reputation;
+		}
+	}
+
+	merged.into_iter().collect()
+}
+
+/// How many pages `count` players take up, at least one
+pub fn pages(count: usize) -> usize { count.div_ceil(PAGE_SIZE).max(1) }
+
+/// Draws page `page` of `ranked`, in the style of [`DBUser::image`]
+pub async fn image(
+	cache_http: impl CacheHttp,
+	ranked: &[(UserId, DBUser)],
+	scope: LeaderboardScope,
+	season: usize,
+	page: usize,
+) -> Result<RgbaImage> {
+	let row_height = 80;
+	let header = 70;
+
+	let entries = ranked
+		.iter()
+		.enumerate()
+		.skip(page * PAGE_SIZE)
+		.take(PAGE_SIZE)
+		.collect::<Vec<_>>();
+
+	// Only the season bars show progress through a level, the rest are relative
+	// to whoever's first
+	let best_weekly_xp = ranked.first().map_or(1, |(_, x)| x.weekly_xp.max(1));
+	let best_total_xp = ranked.first().map_or(1, |(_, x)| x.total_xp.max(1));
+	let best_reputation = ranked.first().map_or(1, |(_, x)| x.reputation.max(1));
+
+	let mut img = canvas((700, header + row_height * entries.len().max(1) as u32 + 20));
+
+	let font_light = light_font()?;
+
+	let text_scale_light = 1.333f32;
+
+	draw_text_mut(
+		&mut img,
+		WHITE,
+		40,
+		20,
+		PxScale::from(30f32 * text_scale_light),
+		&font_light,
+		&scope.title(season),
+	);
+
+	draw_text_mut(
+		&mut img,
+		WHITE,
+		560,
+		32,
+		PxScale::from(16f32 * text_scale_light),
+		&font_light,
+		&format!("Page {}/{}", page + 1, pages(ranked.len())),
+	);
+
+	if entries.is_empty() {
+		draw_text_mut(
+			&mut img,
+			WHITE,
+			40,
+			header as i32 + 20,
+			PxScale::from(20f32 * text_scale_light),
+			&font_light,
+			"Nobody yet",
+		);
+	}
+
+	for (row, (rank, (id, db_user))) in entries.into_iter().enumerate() {
+		let y = header + row as u32 * row_height;
+
+		draw_text_mut(
+			&mut img,
+			WHITE,
+			40,
+			y as i32 + 18,
+			PxScale::from(24f32 * text_scale_light),
+			&font_light,
+			&format!("#{}", rank + 1),
+		);
+
+		let user = id.to_user(&cache_http).await.ok();
+
+		let name = match &user {
+			Some(user) => {
+				draw_avatar(&mut img, user, (110, y as i64 + 6), 60f32).await?;
+
+				display_name(user)
+			}
+			None => id.to_string(),
+		};
+
+		draw_text_mut(
+			&mut img,
+			WHITE,
+			190,
+			y as i32 + 8,
+			PxScale::from(22f32 * text_scale_light),
+			&font_light,
+			&name,
+		);
+
+		let (mut details, filled) = match scope {
+			LeaderboardScope::Week => (
+				format!("{} xp this week", db_user.weekly_xp),
+				db_user.weekly_xp as f32 / best_weekly_xp as f32,
+			),
+			LeaderboardScope::Lifetime => (
+				format!("{} xp in all seasons", db_user.total_xp),
+				db_user.total_xp as f32 / best_total_xp as f32,
+			),
+			LeaderboardScope::Global => (
+				format!("{} xp everywhere", db_user.total_xp),
+				db_user.total_xp as f32 / best_total_xp as f32,
+			),
+			LeaderboardScope::Guild => (
+				format!(
+					"Level {} ({}/{})",
+					db_user.level, db_user.this_levels_xp, db_user.xp_until_next_level
+				),
+				db_user.this_levels_xp as f32 / db_user.xp_until_next_level as f32,
+			),
+			LeaderboardScope::Reputation => (
+				format!("{} rep", db_user.reputation),
+				db_user.reputation as f32 / best_reputation as f32,
+			),
+		};
+
+		// Helpful players get recognized on every board
+		if scope != LeaderboardScope::Reputation && db_user.reputation > 0 {
+			details += &format!(" · {} rep", db_user.reputation);
+		}
+
+		draw_text_mut(
+			&mut img,
+			WHITE,
+			190,
+			y as i32 + 40,
+			PxScale::from(14f32 * text_scale_light),
+			&font_light,
+			&details,
+		);
+
+		draw_bar(
+			&mut img,
+			(440f32, y as f32 + 42f32),
+			(220f32, 16f32),
+			filled,
+		);
+	}
+
+	Ok(img)
+}
@@ -0,0 +1,280 @@
+use serde::{Deserialize, Serialize};
+use std::{
+	fmt::{Display, Formatter},
+	str::FromStr,
+};
+use thiserror::Error;
+
+/// The least XP a level can take when set with `/admin_leveling`, so levels
+/// can't be gained by the thousand
+pub const MIN_LEVEL_XP: u64 = 10;
+
+/// Nobody levels past this, however much XP they have
+pub const MAX_LEVEL: u64 = 10_000;
+
+/// How much XP it takes to go from one level to the next
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum LevelCurve {
+	/// `base + step * (level - 1)`
+	Linear { base: u64, step: u64 },
+
+	/// `base + multiplier * level^exponent / divisor`
+	Polynomial {
+		base: u64,
+		multiplier: u64,
+		exponent: u32,
+		divisor: u64,
+	},
+
+	/// `base`, growing by `percent` every level
+	Exponential { base: u64, percent: u64 },
+
+	/// The XP for every level in order, the last one repeats after the end
+	Table(Vec<u64>),
+}
+
+impl Default for LevelCurve {
+	fn default() -> Self {
+		Self::Polynomial {
+			base: 100,
+			multiplier: 1,
+			exponent: 2,
+			divisor: 2,
+		}
+	}
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum CurveError {
+	#[error("curves are written like `linear 100 10`, `polynomial 100 1 2 2`, `exponential 100 10` or `table 100 150 200`")]
+	Malformed,
+
+	#[error("the divisor can't be 0")]
+	ZeroDivisor,
+
+	#[error("a table needs at least one level")]
+	EmptyTable,
+
+	#[error("every level has to take at least {MIN_LEVEL_XP} XP")]
+	TooCheap,
+}
+
+impl LevelCurve {
+	/// XP needed to get from `level` to the next one, always at least 1
+	pub fn required(&self, level: u64) -> u64 { self.raw_required(level).max(1) }
+
+	/// [`LevelCurve::required`] for `level` and every level after it, without
+	/// working each one out from scratch
+	pub fn requirements(&self, level: u64) -> Requirements<'_> {
+		Requirements {
+			curve: self,
+			level,
+			previous: None,
+		}
+	}
+
+	fn raw_required(&self, level: u64) -> u64 {
+		let steps = level.saturating_sub(1);
+
+		match self {
+			LevelCurve::Linear { base, step } => base.saturating_add(step.saturating_mul(steps)),
+			LevelCurve::Polynomial {
+				base,
+				multiplier,
+				exponent,
+				divisor,
+			} => base.saturating_add(
+				multiplier.saturating_mul(level.saturating_pow(*exponent)) / divisor.max(&1),
+			),
+			LevelCurve::Exponential { base, percent } => {
+				let mut xp = *base;
+
+				for _ in 0..steps {
+					let next = grow(xp, *percent);
+
+					// Small values can round back to themselves, then they stay put
+					if next == xp {
+						break;
+					}
+
+					xp = next;
+				}
+
+				xp
+			}
+			LevelCurve::Table(levels) => levels
+				.get(steps as usize)
+				.or(levels.last())
+				.copied()
+				.unwrap_or(u64::MAX),
+		}
+	}
+}
+
+/// One level of [`LevelCurve::Exponential`], in integer maths so every machine
+/// levels people the same
+fn grow(xp: u64, percent: u64) -> u64 {
+	if xp == u64::MAX {
+		return xp;
+	}
+
+	xp.saturating_mul(100 + percent) / 100
+}
+
+/// See [`LevelCurve::requirements`], it never ends
+pub struct Requirements<'a> {
+	curve: &'a LevelCurve,
+	level: u64,
+
+	/// The last level's requirement before rounding up to 1
+	previous: Option<u64>,
+}
+
+impl Iterator for Requirements<'_> {
+	type Item = u64;
+
+	fn next(&mut self) -> Option<u64> {
+		let xp = match (self.curve, self.previous) {
+			(LevelCurve::Exponential { percent, .. }, Some(previous)) => grow(previous, *percent),
+			_ => self.curve.raw_required(self.level),
+		};
+
+		self.previous = Some(xp);
+		self.level += 1;
+
+		Some(xp.max(1))
+	}
+}
+
+impl Display for LevelCurve {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			LevelCurve::Linear { base, step } => write!(f, "linear {base} {step}"),
+			LevelCurve::Polynomial {
+				base,
+				multiplier,
+				exponent,
+				divisor,
+			} => write!(f, "polynomial {base} {multiplier} {exponent} {divisor}"),
+			LevelCurve::Exponential { base, percent } => write!(f, "exponential {base} {percent}"),
+			LevelCurve::Table(levels) => {
+				f.write_str("table")?;
+
+				for xp in levels {
+					write!(f, " {xp}")?;
+				}
+
+				Ok(())
+			}
+		}
+	}
+}
+
+impl FromStr for LevelCurve {
+	type Err = CurveError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let s = s.to_lowercase();
+		let mut words = s.split([' ', ',']).filter(|x| !x.is_empty());
+
+		let kind = words.next().ok_or(CurveError::Malformed)?;
+
+		let numbers = words
+			.map(|x| x.parse::<u64>().map_err(|_| CurveError::Malformed))
+			.collect::<Result<Vec<_>, _>>()?;
+
+		let curve = match (kind, numbers.as_slice()) {
+			("linear", [base, step]) => LevelCurve::Linear {
+				base: *base,
+				step: *step,
+			},
+			("polynomial", [base, multiplier, exponent, divisor]) => LevelCurve::Polynomial {
+				base: *base,
+				multiplier: *multiplier,
+				exponent: u32::try_from(*exponent).map_err(|_| CurveError::Malformed)?,
+				divisor: *divisor,
+			},
+			("exponential", [base, percent]) => LevelCurve::Exponential {
+				base: *base,
+				percent: *percent,
+			},
+			("table", []) => return Err(CurveError::EmptyTable),
+			("table", levels) => LevelCurve::Table(levels.to_vec()),
+			_ => return Err(CurveError::Malformed),
+		};
+
+		if let LevelCurve::Polynomial { divisor: 0, .. } = curve {
+			return Err(CurveError::ZeroDivisor);
+		}
+
+		// Other curves only grow, so their first level is the cheapest
+		let cheapest = match &curve {
+			LevelCurve::Table(levels) => levels.iter().min().copied().unwrap_or(0),
+			curve => curve.raw_required(1),
+		};
+
+		if cheapest < MIN_LEVEL_XP {
+			return Err(CurveError::TooCheap);
+		}
+
+		Ok(curve)
+	}
+}
+
+/// How much XP a message gives, from how many distinct letters it has
+///
+/// `(letters / per_letters)^exponent * multiplier`, rounded and capped at `cap`
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MessageXp {
+	pub per_letters: u64,
+	pub exponent: u32,
+	pub multiplier: u64,
+	pub cap: u64,
+}
+
+impl Default for MessageXp {
+	fn default() -> Self {
+		Self {
+			per_letters: 15,
+			exponent: 2,
+			multiplier: 5,
+			cap: 15,
+		}
+	}
+}
+
+impl MessageXp {
+	pub fn xp(&self, letters: usize) -> u64 {
+		let curve = ((letters as f64) / (self.per_letters.max(1) as f64))
+			.powf(self.exponent as f64)
+			* self.multiplier as f64;
+
+		curve.min(self.cap as f64).round() as u64
+	}
+}
+
+/// A guild's leveling curves
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Leveling {
+	#[serde(default)]
+	pub curve: LevelCurve,
+
+	#[serde(default)]
+	pub message: MessageXp,
+
+	/// XP for every minute spent active in a voice channel
+	#[serde(default = "default_voice_per_minute")]
+	pub voice_per_minute: u64,
+}
+
+fn default_voice_per_minute() -> u64 { 2 }
+
+impl Default for Leveling {
+	fn default() -> Self {
+		Self {
+			curve: LevelCurve::default(),
+			message: MessageXp::default(),
+			voice_per_minute: default_voice_per_minute(),
+		}
+	}
+}
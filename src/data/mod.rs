@@ -1,70 +1,483 @@
-use std::{fmt::Debug, path::Path};
-
-use crate::data::state::SideChannel;
-use eyre::{OptionExt, Result};
+use crate::{
+	config::{get_backup_count, get_snapshot_interval},
+	data::{
+		bus::{Committed, Subscriber},
+		envelope::{EventEnvelope, EventMeta},
+		snapshot::Snapshot,
+		storage::{backup, Storage, StorageBackend},
+	},
+};
+use eyre::{eyre, OptionExt, Result};
+use rusqlite::ErrorCode;
 use state::{DBEvent, DBState};
+use std::{collections::HashSet, fmt::Debug, path::Path};
+use thiserror::Error;
 
-mod battle;
+pub mod announcements;
+pub mod anti_spam;
+pub mod battle;
+pub mod bus;
+pub mod daily;
+pub mod drawing;
+pub mod envelope;
 pub mod items;
+pub mod journal;
+pub mod leaderboard;
+pub mod leveling;
 pub mod places;
+pub mod reputation;
 pub mod rng;
+pub mod schema;
+pub mod season;
+pub mod snapshot;
 pub mod state;
+pub mod storage;
 pub mod user;
 
 #[derive(Debug)]
 pub struct Database {
 	state: DBState,
-	timeline: Vec<DBEvent>,
-	path: Box<Path>,
+	timeline: Vec<EventEnvelope>,
+	compacted: usize,
+	storage: Box<dyn Storage>,
+	subscribers: Vec<Box<dyn Subscriber>>,
 }
 
 impl Database {
-	pub fn new(path: Box<Path>) -> Result<Self> {
+	/// Opens the database at `path` and backs it up
+	///
+	/// If it doesn't load because it's corrupt, it's moved aside and replaced
+	/// by the newest backup that does. Events added after that backup are lost,
+	/// but the bot keeps running.
+	pub fn open(backend: StorageBackend, path: &Path) -> Result<Self> {
+		let error = match backend.open_at(path).and_then(Self::new) {
+			Ok(mut me) => {
+				me.storage.backup(get_backup_count())?;
+				return Ok(me);
+			}
+			Err(err) => err,
+		};
+
+		if !is_corruption(&error) {
+			return Err(error);
+		}
+
+		let backups = backup::list(path)?;
+
+		if backups.is_empty() {
+			return Err(error);
+		}
+
+		let aside = backup::set_aside(backend, path)?;
+
+		println!(
+			"warn: {} does not load, moved it to {}: {error:#}",
+			path.display(),
+			aside.display()
+		);
+
+		for backup in backups {
+			backup::restore(backend, path, &backup)?;
+
+			match backend.open_at(path).and_then(Self::new) {
+				Ok(me) => {
+					println!("warn: restored the backup {}", backup.display());
+					return Ok(me);
+				}
+				Err(err) => println!(
+					"warn: the backup {} does not load either: {err:#}",
+					backup.display()
+				),
+			}
+		}
+
+		// Nothing worked, so leave things as we found them
+		backup::restore(backend, path, &aside)?;
+
+		Err(error)
+	}
+
+	pub fn new(storage: Box<dyn Storage>) -> Result<Self> { Self::load(storage, true) }
+
+	/// Loads the database without writing anything, not even a snapshot, so
+	/// it can be looked at while the bot is down
+	pub fn inspect(storage: Box<dyn Storage>) -> Result<Self> { Self::load(storage, false) }
+
+	fn load(mut storage: Box<dyn Storage>, write: bool) -> Result<Self> {
+		let loaded_timeline = storage.load_events()?;
+
+		// Start from the latest snapshot, if there is one that fits the timeline
+		let snapshot = storage.load_snapshot()?.unwrap_or_default();
+
+		// A compaction that didn't finish can leave a snapshot from before it
+		if let Some(compacted) = storage.load_compacted()? {
+			if compacted != snapshot.compacted {
+				return Err(eyre!(
+					"the timeline starts after event {compacted} but the snapshot expects it to \
+					 start after event {}",
+					snapshot.compacted
+				));
+			}
+		}
+
+		let replay_from = snapshot.offset - snapshot.compacted;
+
+		if replay_from > loaded_timeline.len() {
+			return Err(eyre!(
+				"snapshot covers {} events but the timeline only has {}",
+				snapshot.offset,
+				snapshot.compacted + loaded_timeline.len()
+			));
+		}
+
 		let mut me = Self {
-			state: DBState::default(),
-			timeline: vec![],
-			path,
+			state: snapshot.state,
+			timeline: loaded_timeline,
+			compacted: snapshot.compacted,
+			storage,
+			subscribers: vec![],
 		};
 
-		let loaded_timeline = if me.path.exists() {
-			let file_content = std::fs::read_to_string(&me.path)?;
-			serde_json::from_str::<Vec<DBEvent>>(&file_content)?
+		// If an event the snapshot already includes was reverted after it was
+		// taken, the snapshot is no good and we have to start over
+		let snapshot_is_stale = me.timeline[replay_from..]
+			.iter()
+			.any(|x| matches!(x.event, DBEvent::Revert { index } if index < snapshot.offset));
+
+		if snapshot_is_stale {
+			me.rebuild()?;
 		} else {
-			vec![]
-		};
+			let reverted = me.reverted();
 
-		for entry in &loaded_timeline {
-			(me.state, _) = entry.reduce_state(me.state.clone());
+			for (idx, entry) in me.timeline.iter().enumerate().skip(replay_from) {
+				if !reverted.contains(&(me.compacted + idx)) {
+					me.state = entry.apply(me.state);
+				}
+			}
 		}
 
-		me.timeline = loaded_timeline;
-
-		me.save()?;
+		if write && me.timeline.len() - replay_from >= get_snapshot_interval() {
+			me.snapshot()?;
+		}
 
 		Ok(me)
 	}
 
 	pub fn state(&self) -> &DBState { &self.state }
 
-	fn save(&self) -> Result<()> {
-		let file_content = serde_json::to_string_pretty(&self.timeline)?;
-		std::fs::write(&self.path, file_content)?;
+	/// Calls `subscriber` for every event added from now on
+	pub fn subscribe<T: Subscriber + 'static>(&mut self, subscriber: T) {
+		self.subscribers.push(Box::new(subscriber));
+	}
+
+	/// Every event that hasn't been compacted away, oldest first
+	pub fn timeline(&self) -> &[EventEnvelope] { &self.timeline }
+
+	/// The amount of events that have ever been added, including compacted ones
+	pub fn len(&self) -> usize { self.compacted + self.timeline.len() }
+
+	pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+	/// The indices of every event in the live timeline that has been reverted
+	pub fn reverted(&self) -> HashSet<usize> {
+		self.timeline
+			.iter()
+			.filter_map(|x| match x.event {
+				DBEvent::Revert { index } => Some(index),
+				_ => None,
+			})
+			.collect()
+	}
+
+	/// Replays the live timeline from the start, calling `visit` with the index
+	/// of every event, the event, and the state right after it
+	///
+	/// Reverted events are still visited, but leave the state untouched.
+	pub fn replay<T>(&mut self, visit: T) -> Result<()>
+	where
+		T: FnMut(usize, &EventEnvelope, &DBState),
+	{
+		let base = self.base_state()?;
+
+		replay(
+			base,
+			self.compacted,
+			&self.timeline,
+			&self.reverted(),
+			visit,
+		);
+
+		Ok(())
+	}
+
+	/// A copy of the live timeline to [`History::replay`] later, so slow
+	/// replays don't have to keep the database locked
+	pub fn history(&mut self) -> Result<History> {
+		Ok(History {
+			base: self.base_state()?,
+			compacted: self.compacted,
+			timeline: self.timeline.clone(),
+			reverted: self.reverted(),
+		})
+	}
+
+	/// The state the live timeline starts from
+	fn base_state(&mut self) -> Result<DBState> {
+		if self.compacted == 0 {
+			return Ok(DBState::default());
+		}
+
+		let base = self
+			.storage
+			.load_compaction_snapshot()?
+			.ok_or_eyre("timeline was compacted but the compaction snapshot is missing")?;
+
+		if base.offset != self.compacted {
+			return Err(eyre!(
+				"the compaction snapshot covers {} events but {} were compacted",
+				base.offset,
+				self.compacted
+			));
+		}
+
+		Ok(base.state)
+	}
+
+	/// Checks that replaying the live timeline ends up at the state that was
+	/// loaded from the snapshot
+	pub fn validate(&mut self) -> Result<()> {
+		let loaded = serde_json::to_value(&self.state)?;
+
+		let mut replayed = None;
+
+		self.replay(|_, _, state| replayed = Some(state.clone()))?;
+
+		if let Some(replayed) = replayed {
+			if serde_json::to_value(&replayed)? != loaded {
+				return Err(eyre!(
+					"replaying the timeline gives a different state than the snapshot"
+				));
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Recomputes the state from the start of the live timeline
+	fn rebuild(&mut self) -> Result<()> {
+		let mut state = None;
+		let last = self.len() - 1;
+
+		self.replay(|idx, _, x| {
+			if idx == last {
+				state = Some(x.clone())
+			}
+		})?;
+
+		if let Some(state) = state {
+			self.state = state;
+		}
+
+		Ok(())
+	}
+
+	/// Stores the current state so the next startup only has to replay events
+	/// added after this point
+	pub fn snapshot(&mut self) -> Result<()> {
+		let snapshot = Snapshot {
+			compacted: self.compacted,
+			offset: self.len(),
+			state: self.state.clone(),
+		};
+
+		self.storage.store_snapshot(&snapshot)
+	}
+
+	/// Folds every event so far into a snapshot, moving the raw events out of
+	/// the timeline and into an archive, returns where the archive went
+	pub fn compact(&mut self) -> Result<String> {
+		let snapshot = Snapshot {
+			compacted: self.len(),
+			offset: self.len(),
+			state: self.state.clone(),
+		};
 
-		std::fs::write(
-			self.path.to_str().ok_or_eyre("Invalid path")?.to_string() + ".log",
-			format!("{:#?}", self.state),
+		let archive = self.storage.compact(&snapshot)?;
+
+		// Only once it's stored, so a failed compaction leaves us as we were
+		self.compacted = snapshot.compacted;
+		self.timeline.clear();
+
+		// Older backups don't line up with the archive anymore
+		self.storage.backup(get_backup_count())?;
+
+		Ok(archive)
+	}
+
+	/// Seeds a fresh timeline with a state exported from another database
+	pub fn import(&mut self, state: DBState, meta: EventMeta) -> Result<()> {
+		if !self.is_empty() {
+			return Err(eyre!(
+				"can only import into an empty database, this one has {} events",
+				self.len()
+			));
+		}
+
+		self.add(
+			DBEvent::Import {
+				state: Box::new(state),
+			},
+			meta,
 		)?;
 
 		Ok(())
 	}
 
-	pub fn add(&mut self, event: DBEvent) -> Result<SideChannel> {
-		let (state, side_channel) = event.reduce_state(self.state.clone());
+	/// Adds an event to the timeline, unless it can't be applied, in which
+	/// case the [`state::ReduceError`] is returned
+	pub fn add(&mut self, event: DBEvent, meta: EventMeta) -> Result<()> {
+		if let DBEvent::Revert { index } = event {
+			self.check_revert(index)?;
+		}
+
+		let envelope = EventEnvelope { meta, event };
+
+		let state = envelope.reduce_state(&self.state)?;
+
+		self.storage.append_event(&envelope)?;
+
+		let is_revert = matches!(envelope.event, DBEvent::Revert { .. });
+
+		let before = std::mem::replace(&mut self.state, state);
+		self.timeline.push(envelope);
+
+		if is_revert {
+			self.rebuild()?;
+		}
+
+		if self.len().is_multiple_of(get_snapshot_interval()) {
+			self.snapshot()?;
+			self.storage.backup(get_backup_count())?;
+		}
+
+		let committed = Committed {
+			index: self.len() - 1,
+			envelope: &self.timeline[self.timeline.len() - 1],
+			before: &before,
+			after: &self.state,
+		};
+
+		for subscriber in &self.subscribers {
+			subscriber.committed(&committed);
+		}
+
+		Ok(())
+	}
+
+	fn check_revert(&self, index: usize) -> Result<(), RevertError> {
+		if index >= self.len() {
+			return Err(RevertError::DoesNotExist(index));
+		}
+
+		if index < self.compacted {
+			return Err(RevertError::Compacted(index));
+		}
+
+		if let DBEvent::Revert { .. } = self.timeline[index - self.compacted].event {
+			return Err(RevertError::IsARevert(index));
+		}
 
-		self.state = state;
-		self.timeline.push(event);
-		self.save()?;
+		if self.reverted().contains(&index) {
+			return Err(RevertError::AlreadyReverted(index));
+		}
 
-		Ok(side_channel)
+		Ok(())
 	}
 }
+
+/// Whether `error` means the database is broken in a way a backup can fix,
+/// and not that a newer build wrote it or it couldn't be read at all
+fn is_corruption(error: &eyre::Report) -> bool {
+	!error.chain().any(|x| {
+		x.is::<schema::TooNew>()
+			|| x.is::<std::io::Error>()
+			|| x.downcast_ref::<rusqlite::Error>().is_some_and(|x| {
+				!matches!(
+					x.sqlite_error_code(),
+					None | Some(ErrorCode::NotADatabase | ErrorCode::DatabaseCorrupt)
+				)
+			})
+	})
+}
+
+/// The live timeline as it was when [`Database::history`] was called
+#[derive(Clone, Debug)]
+pub struct History {
+	base: DBState,
+
+	/// How many events were compacted away before the first one here
+	pub compacted: usize,
+	pub timeline: Vec<EventEnvelope>,
+
+	/// See [`Database::reverted`]
+	pub reverted: HashSet<usize>,
+}
+
+impl History {
+	/// Like [`Database::replay`]
+	pub fn replay<T>(&self, visit: T)
+	where
+		T: FnMut(usize, &EventEnvelope, &DBState),
+	{
+		replay(
+			self.base.clone(),
+			self.compacted,
+			&self.timeline,
+			&self.reverted,
+			visit,
+		);
+	}
+}
+
+fn replay<T>(
+	mut state: DBState,
+	compacted: usize,
+	timeline: &[EventEnvelope],
+	reverted: &HashSet<usize>,
+	mut visit: T,
+) where
+	T: FnMut(usize, &EventEnvelope, &DBState),
+{
+	for (idx, envelope) in timeline.iter().enumerate() {
+		let idx = compacted + idx;
+
+		if !reverted.contains(&idx) {
+			state = envelope.apply(state);
+		}
+
+		visit(idx, envelope, &state);
+	}
+}
+
+impl EventEnvelope {
+	/// Applies an event that's already in the timeline
+	///
+	/// Events that fail aren't added anymore, but older timelines can still
+	/// have some, they leave the state untouched.
+	fn apply(&self, state: DBState) -> DBState { self.reduce_state(&state).unwrap_or(state) }
+}
+
+#[derive(Error, Debug)]
+pub enum RevertError {
+	#[error("event #{0} does not exist")]
+	DoesNotExist(usize),
+
+	#[error("event #{0} has been compacted")]
+	Compacted(usize),
+
+	#[error("event #{0} is a revert itself")]
+	IsARevert(usize),
+
+	#[error("event #{0} has already been reverted")]
+	AlreadyReverted(usize),
+}
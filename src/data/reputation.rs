@@ -0,0 +1,84 @@
+use crate::data::daily::day;
+use serde::{Deserialize, Serialize};
+use serenity::all::{Timestamp, UserId};
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// Seconds before someone can give the same member reputation again
+pub const COOLDOWN: i64 = 12 * 60 * 60;
+
+/// How much reputation someone can give out in a day
+pub const DAILY_BUDGET: u32 = 3;
+
+/// Reactions on a message that thank its author
+pub const THANKS_REACTIONS: [&str; 3] = ["🙏", "❤️", "⭐"];
+
+/// How reputation was given
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum RepSource {
+	/// With `/rep`
+	Command,
+
+	/// By reacting with one of [`THANKS_REACTIONS`]
+	Thanks,
+}
+
+#[derive(Error, Debug)]
+pub enum RepError {
+	#[error("you can't give yourself reputation")]
+	SelfRep,
+
+	#[error("you gave them reputation recently, try again <t:{0}:R>")]
+	Cooldown(i64),
+
+	#[error("you've given out all {DAILY_BUDGET} reputation for today")]
+	BudgetSpent,
+}
+
+/// The reputation a user has given out, timestamps are in unix seconds
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RepGiving {
+	/// When they last gave each member reputation, only while it's on
+	/// [`COOLDOWN`]
+	pub last_given: HashMap<UserId, i64>,
+
+	/// The [`day`] that `given_today` counts
+	pub day: i64,
+	pub given_today: u32,
+}
+
+impl RepGiving {
+	/// Checks `giver` can give `receiver` reputation at `at`, and remembers it
+	pub fn give(
+		&mut self,
+		giver: &UserId,
+		receiver: &UserId,
+		at: &Timestamp,
+	) -> Result<(), RepError> {
+		if giver == receiver {
+			return Err(RepError::SelfRep);
+		}
+
+		let now = at.unix_timestamp();
+
+		self.last_given.retain(|_, x| now - *x < COOLDOWN);
+
+		if let Some(last) = self.last_given.get(receiver) {
+			return Err(RepError::Cooldown(last + COOLDOWN));
+		}
+
+		if self.day != day(at) {
+			self.day = day(at);
+			self.given_today = 0;
+		}
+
+		if self.given_today >= DAILY_BUDGET {
+			return Err(RepError::BudgetSpent);
+		}
+
+		self.given_today += 1;
+		self.last_given.insert(*receiver, now);
+
+		Ok(())
+	}
+}
@@ -1,42 +1,266 @@
-use rand::random;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::{
-	fmt::{Debug, Formatter},
+	fmt::{Display, Formatter},
 	ops::Range,
+	str::FromStr,
 };
+use thiserror::Error;
 
-#[derive(Serialize, Deserialize, Clone)]
-pub struct Random(u32);
+/// Where a [`Rng`] starts from
+///
+/// Anything random that changes the state is rolled from a seed that's stored
+/// with its event, so replaying the event rolls the same way.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Seed(u64);
 
-impl Default for Random {
+impl Seed {
+	/// A fresh seed from the operating system
+	pub fn new() -> Self { Self(rand::random()) }
+
+	pub fn rng(&self) -> Rng { Rng(self.0) }
+
+	/// The SHA-256 of this seed as written by [`Display`], safe to show before
+	/// the seed is used, so people can check it wasn't changed afterwards
+	pub fn commitment(&self) -> String { sha256_hex(&self.to_string()) }
+}
+
+impl Default for Seed {
 	fn default() -> Self { Self::new() }
 }
 
-impl Random {
-	pub fn new() -> Self { Self(random()) }
+impl Display for Seed {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result { write!(f, "{:016x}", self.0) }
+}
 
-	pub fn get(&self, range: Range<f32>) -> f32 {
-		range.start + self.0 as f32 / i32::MAX as f32 * (range.end - range.start)
+impl FromStr for Seed {
+	type Err = std::num::ParseIntError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> { u64::from_str_radix(s, 16).map(Self) }
+}
+
+// Seeds are written as hex, so they look the same in the journal as in Discord
+impl Serialize for Seed {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_str(&self.to_string())
 	}
 }
 
-#[derive(Serialize, Deserialize, Clone)]
-pub struct Chance(Random);
+impl<'de> Deserialize<'de> for Seed {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		String::deserialize(deserializer)?
+			.parse()
+			.map_err(serde::de::Error::custom)
+	}
+}
+
+/// SplitMix64, picked over `rand`'s generators because its output is fully
+/// specified and won't change under us between versions
+#[derive(Clone, Debug)]
+pub struct Rng(u64);
+
+impl Rng {
+	pub fn next_u64(&mut self) -> u64 {
+		self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+		let mut z = self.0;
+		z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+		z ^ (z >> 31)
+	}
+
+	/// A number in `range`, every one equally likely
+	pub fn range(&mut self, range: Range<u64>) -> u64 {
+		assert!(!range.is_empty(), "can't roll in an empty range");
+
+		let span = range.end - range.start;
+
+		// Throw away the top few values that would make lower ones more likely
+		let limit = u64::MAX - u64::MAX % span;
+
+		loop {
+			let x = self.next_u64();
+
+			if x < limit {
+				return range.start + x % span;
+			}
+		}
+	}
+
+	/// A number in `0..1`
+	pub fn unit(&mut self) -> f64 { (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64 }
+
+	/// True with probability `chance`
+	pub fn chance(&mut self, chance: f64) -> bool { self.unit() < chance }
+
+	/// One of `options`, picked with likelihood proportional to its weight,
+	/// `None` if every weight is zero
+	pub fn choose_weighted<'a, T>(&mut self, options: &'a [(T, u64)]) -> Option<&'a T> {
+		let total = options.iter().map(|(_, weight)| weight).sum::<u64>();
+
+		if total == 0 {
+			return None;
+		}
+
+		let mut roll = self.range(0..total);
+
+		for (option, weight) in options {
+			if roll < *weight {
+				return Some(option);
+			}
+
+			roll -= weight;
+		}
+
+		unreachable!("roll is below the total weight")
+	}
+}
+
+/// Dice in the usual notation, like `d20`, `3d6` or `2d8+4`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Dice {
+	pub count: u32,
+	pub sides: u64,
+	pub modifier: i64,
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum DiceError {
+	#[error("dice are written like 2d6+3")]
+	Malformed,
 
-impl Debug for Chance {
+	#[error("can roll between 1 and {MAX_DICE} dice at once")]
+	BadCount,
+
+	#[error("dice need between 1 and {MAX_SIDES} sides")]
+	BadSides,
+
+	#[error("the modifier can be at most {MAX_MODIFIER} either way")]
+	BadModifier,
+}
+
+const MAX_DICE: u32 = 100;
+const MAX_SIDES: u64 = 1000;
+const MAX_MODIFIER: i64 = 1_000_000;
+
+/// The outcome of rolling [`Dice`]
+#[derive(Clone, Debug)]
+pub struct DiceRoll {
+	pub rolls: Vec<u64>,
+	pub total: i64,
+}
+
+impl Dice {
+	pub fn roll(&self, rng: &mut Rng) -> DiceRoll {
+		let rolls = (0..self.count)
+			.map(|_| rng.range(1..self.sides + 1))
+			.collect::<Vec<_>>();
+
+		DiceRoll {
+			total: (rolls.iter().sum::<u64>() as i64).saturating_add(self.modifier),
+			rolls,
+		}
+	}
+}
+
+impl Display for Dice {
 	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-		f.write_str(&format!("Chance({})", self.threshold()))
+		write!(f, "{}d{}", self.count, self.sides)?;
+
+		match self.modifier {
+			0 => Ok(()),
+			x => write!(f, "{x:+}"),
+		}
 	}
 }
 
-impl Chance {
-	pub fn new() -> Self { Self(Random::new()) }
+impl FromStr for Dice {
+	type Err = DiceError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let s = s.trim().to_lowercase();
+
+		let (count, rest) = s.split_once('d').ok_or(DiceError::Malformed)?;
+
+		let (sides, modifier) = match rest.find(['+', '-']) {
+			Some(idx) => (
+				&rest[..idx],
+				rest[idx..].parse().map_err(|_| DiceError::Malformed)?,
+			),
+			None => (rest, 0),
+		};
+
+		let count = match count {
+			"" => 1,
+			x => x.parse().map_err(|_| DiceError::Malformed)?,
+		};
+
+		let sides = sides.parse().map_err(|_| DiceError::Malformed)?;
 
-	pub fn threshold(&self) -> f32 { self.0.get(0f32..1f32) }
+		if !(1..=MAX_DICE).contains(&count) {
+			return Err(DiceError::BadCount);
+		}
 
-	pub fn eval(&self, chance: f32) -> bool { self.threshold() <= chance }
+		if !(1..=MAX_SIDES).contains(&sides) {
+			return Err(DiceError::BadSides);
+		}
+
+		if !(-MAX_MODIFIER..=MAX_MODIFIER).contains(&modifier) {
+			return Err(DiceError::BadModifier);
+		}
+
+		Ok(Self {
+			count,
+			sides,
+			modifier,
+		})
+	}
 }
 
-impl Default for Chance {
-	fn default() -> Self { Self::new() }
+/// A coin flip anyone can check
+///
+/// Before a flip, only the [`Seed::commitment`] of `seed` is shown. The flip
+/// then mixes in `client_seed`, which the bot doesn't pick. Heads is when the
+/// first hex digit of `sha256("{seed}:{client_seed}")` is below 8, which
+/// players can recompute once `seed` is revealed.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FairFlip {
+	pub seed: Seed,
+	pub client_seed: String,
+
+	/// The seed the flipper's next flip will use, kept secret until then
+	pub next: Seed,
+}
+
+impl FairFlip {
+	pub fn hash(&self) -> String { sha256_hex(&format!("{}:{}", self.seed, self.client_seed)) }
+
+	pub fn heads(&self) -> bool { self.hash().as_bytes()[0] < b'8' }
+}
+
+/// How a coin flip landed
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum Flip {
+	/// Recorded before flips were provably fair, only the outcome is known
+	Legacy {
+		heads: bool,
+	},
+
+	Fair(FairFlip),
+}
+
+impl Flip {
+	pub fn heads(&self) -> bool {
+		match self {
+			Flip::Legacy { heads } => *heads,
+			Flip::Fair(flip) => flip.heads(),
+		}
+	}
+}
+
+fn sha256_hex(input: &str) -> String {
+	Sha256::digest(input.as_bytes())
+		.iter()
+		.map(|x| format!("{x:02x}"))
+		.collect()
 }
@@ -0,0 +1,223 @@
+use crate::{
+	config::get_legacy_guild,
+	data::{
+		envelope::{EventEnvelope, EventMeta, EventSource},
+		state::{DBProgress, DBState},
+	},
+};
+use eyre::{eyre, Result};
+use serde::{de::Error, Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+/// The version [`encode`] writes events as
+///
+/// Bump this whenever the serialized shape of [`EventEnvelope`] changes, and
+/// add an upgrade from the previous version to [`UPGRADES`].
+pub const CURRENT_VERSION: u32 = 7;
+
+type Body = Map<String, Value>;
+
+/// An event written by a newer build than this one, which can't be read
+/// without losing what the newer build added
+#[derive(Error, Debug)]
+#[error("event has version {0}, but the newest version this build knows is {CURRENT_VERSION}")]
+pub struct TooNew(pub u32);
+
+/// `UPGRADES[n]` turns the body of a version `n` event into the body of a
+/// version `n + 1` event
+static UPGRADES: &[fn(Body) -> Result<Body>] = &[
+	// 0 -> 1: events were written bare, without an envelope. The shape of the
+	// event itself didn't change
+	Ok,
+	// 1 -> 2: events gained metadata
+	|mut body| {
+		body.insert(
+			"meta".to_string(),
+			serde_json::to_value(EventMeta::unknown())?,
+		);
+
+		Ok(body)
+	},
+	// 2 -> 3: progress became per guild, events that weren't recorded with a
+	// guild go to the legacy one
+	|mut body| {
+		if let Some(Value::Object(meta)) = body.get_mut("meta") {
+			if meta.get("guild") == Some(&Value::Null)
+				&& meta.get("source") == Some(&serde_json::to_value(EventSource::Unknown)?)
+			{
+				meta.insert(
+					"guild".to_string(),
+					serde_json::to_value(get_legacy_guild())?,
+				);
+			}
+		}
+
+		Ok(body)
+	},
+	// 3 -> 4: events gained the channel they happened in
+	|mut body| {
+		if let Some(Value::Object(meta)) = body.get_mut("meta") {
+			meta.insert("channel".to_string(), Value::Null);
+		}
+
+		Ok(body)
+	},
+	// 4 -> 5: coin flips stopped storing a raw roll, which used to be read as
+	// `roll / i32::MAX <= 0.5`. Keep the outcome that gave
+	|mut body| {
+		if let Some(Value::Object(flip)) = body.get_mut("event").and_then(|x| x.get_mut("CoinFlip"))
+		{
+			if let Some(roll) = flip.remove("chance").and_then(|x| x.as_u64()) {
+				let heads = roll as u32 as f32 / i32::MAX as f32 <= 0.5;
+
+				flip.insert(
+					"flip".to_string(),
+					serde_json::json!({ "Legacy": { "heads": heads } }),
+				);
+			}
+		}
+
+		Ok(body)
+	},
+	// 5 -> 6: messages gained a fingerprint and time based anti-spam, which
+	// older messages weren't held to
+	|mut body| {
+		if let Some(Value::Object(message)) = body
+			.get_mut("event")
+			.and_then(|x| x.get_mut("UserSendMessage"))
+		{
+			message.insert("fingerprint".to_string(), Value::Null);
+			message.insert("legacy_rules".to_string(), Value::Bool(true));
+		}
+
+		Ok(body)
+	},
+	// 6 -> 7: events gained the message that caused them
+	|mut body| {
+		if let Some(Value::Object(meta)) = body.get_mut("meta") {
+			meta.insert("message".to_string(), Value::Null);
+		}
+
+		Ok(body)
+	},
+];
+
+/// The fields of [`DBProgress`], which used to sit directly on [`DBState`]
+const PROGRESS_FIELDS: &[&str] = &[
+	"counter",
+	"people_who_counted",
+	"flips_in_a_row",
+	"users",
+	"last_typed_user",
+];
+
+/// How events are stored on disk, the body holds the fields of
+/// [`EventEnvelope`] as they were in `version`
+#[derive(Serialize, Deserialize)]
+struct Stored {
+	version: u32,
+
+	#[serde(flatten)]
+	body: Body,
+}
+
+/// Serializes an event as the current version
+pub fn encode(envelope: &EventEnvelope) -> Result<String> {
+	let Value::Object(body) = serde_json::to_value(envelope)? else {
+		return Err(eyre!("envelope did not serialize as an object"));
+	};
+
+	Ok(serde_json::to_string(&Stored {
+		version: CURRENT_VERSION,
+		body,
+	})?)
+}
+
+/// Deserializes an event written by any version, upgrading it if needed
+pub fn decode(raw: &[u8]) -> Result<EventEnvelope> { decode_value(serde_json::from_slice(raw)?) }
+
+/// Like [`decode`], for an event that has already been parsed as JSON
+pub fn decode_value(raw: Value) -> Result<EventEnvelope> {
+	let Stored {
+		mut version,
+		mut body,
+	} = if is_versioned(&raw) {
+		serde_json::from_value::<Stored>(raw)?
+	} else {
+		// Before envelopes existed the event was written on its own
+		Stored {
+			version: 0,
+			body: Body::from_iter([("event".to_string(), raw)]),
+		}
+	};
+
+	if version > CURRENT_VERSION {
+		return Err(TooNew(version).into());
+	}
+
+	while version < CURRENT_VERSION {
+		body = UPGRADES[version as usize](body)?;
+		version += 1;
+	}
+
+	Ok(serde_json::from_value(Value::Object(body))?)
+}
+
+/// Deserializes a [`DBState`], moving the progress of states from before it
+/// was kept per guild into the legacy guild, and working out lifetime XP for
+/// users from before it was kept
+pub fn decode_state(raw: Value) -> Result<DBState> {
+	let Value::Object(mut body) = raw else {
+		return Err(eyre!("state is not an object"));
+	};
+
+	let mut state = if body.contains_key("global") {
+		serde_json::from_value(Value::Object(body))?
+	} else {
+		let mut progress = Body::new();
+
+		for field in PROGRESS_FIELDS {
+			if let Some(value) = body.remove(*field) {
+				progress.insert(field.to_string(), value);
+			}
+		}
+
+		body.insert(
+			"global".to_string(),
+			serde_json::to_value(DBProgress::default())?,
+		);
+
+		let mut state: DBState = serde_json::from_value(Value::Object(body))?;
+
+		state
+			.servers
+			.entry(get_legacy_guild())
+			.or_default()
+			.progress = serde_json::from_value(Value::Object(progress))?;
+
+		state
+	};
+
+	let progresses = state
+		.servers
+		.values_mut()
+		.map(|x| &mut x.progress)
+		.chain([&mut state.global]);
+
+	for user in progresses.flat_map(|x| x.users.values_mut()) {
+		user.backfill_total_xp();
+	}
+
+	Ok(state)
+}
+
+/// [`decode_state`] for `#[serde(deserialize_with)]`
+pub fn deserialize_state<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DBState, D::Error> {
+	decode_state(Value::deserialize(deserializer)?).map_err(D::Error::custom)
+}
+
+fn is_versioned(raw: &Value) -> bool {
+	raw.as_object()
+		.is_some_and(|x| x.contains_key("version") && x.contains_key("event"))
+}
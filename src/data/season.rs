@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+use serenity::all::{Timestamp, UserId};
+
+/// Where a user finished a season
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SeasonStanding {
+	pub user: UserId,
+	pub level: u64,
+	pub this_levels_xp: u64,
+
+	/// Everything they gained during the season
+	pub xp: u64,
+}
+
+/// A season that has ended
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SeasonRecord {
+	/// Seasons are counted from 1
+	pub number: usize,
+	pub ended: Timestamp,
+
+	/// Best first, only users that gained XP during the season
+	pub standings: Vec<SeasonStanding>,
+}
@@ -0,0 +1,33 @@
+use crate::data::{schema, state::DBState, storage::write_atomic};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// A serialized [`DBState`] along with how much of the timeline it covers
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Snapshot {
+	/// How many events have been archived out of the timeline file
+	pub compacted: usize,
+
+	/// How many events (counting from the very first one) `state` is the
+	/// result of
+	pub offset: usize,
+
+	#[serde(deserialize_with = "schema::deserialize_state")]
+	pub state: DBState,
+}
+
+impl Snapshot {
+	pub fn load(path: &Path) -> eyre::Result<Option<Self>> {
+		if !path.exists() {
+			return Ok(None);
+		}
+
+		let file_content = std::fs::read_to_string(path)?;
+
+		Ok(Some(serde_json::from_str(&file_content)?))
+	}
+
+	pub fn store(&self, path: &Path) -> eyre::Result<()> {
+		write_atomic(path, serde_json::to_string(self)?.as_bytes())
+	}
+}
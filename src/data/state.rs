@@ -1,27 +1,80 @@
 use crate::{
 	data::{
+		announcements::AnnouncementConfig,
+		anti_spam::{AntiSpamConfig, XpActivity},
+		daily::DailyReward,
+		envelope::EventEnvelope,
 		items::InventoryItem,
-		rng::Chance,
+		leveling::Leveling,
+		reputation::{RepError, RepSource},
+		rng::{Dice, Flip, Seed},
+		schema,
+		season::{SeasonRecord, SeasonStanding},
 		user::{DBUser, DBUserError},
 	},
-	systems::autoconfig::data::{ServerConfigChannelId, ServerConfigRoleId},
-	utils::calculate_length_to_xp,
+	systems::autoconfig::data::{level_reward_role, ServerConfigChannelId, ServerConfigRoleId},
 };
-use serde::{Deserialize, Serialize};
-use serenity::all::{ChannelId, GuildId, RoleId, UserId};
-use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Deserializer, Serialize};
+use serenity::all::{ChannelId, GuildId, RoleId, Timestamp, UserId};
+use std::{
+	cmp::Reverse,
+	collections::{BTreeMap, BTreeSet, HashMap},
+	ops::RangeBounds,
+	sync::LazyLock,
+};
+use thiserror::Error;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum DBEvent {
 	Counter {
 		user: UserId,
 	},
 	CoinFlip {
-		chance: Chance,
+		flip: Flip,
+	},
+	/// A `/roll`, rolled from `seed`, which the user's last roll committed to
+	DiceRoll {
+		user: UserId,
+		dice: Dice,
+		seed: Seed,
+
+		/// The seed the user's next roll will use, kept secret until then
+		next: Seed,
 	},
 	UserSendMessage {
 		user: UserId,
 		length: usize,
+
+		/// See [`crate::utils::Fingerprint`], we don't keep the message itself
+		fingerprint: Option<u64>,
+
+		/// Recorded before [`AntiSpamConfig`], only the old rule of ignoring
+		/// the same user twice in a row applies
+		legacy_rules: bool,
+	},
+	/// Minutes a user was active in voice, recorded every so often while
+	/// they're there and once more when they leave, see
+	/// [`crate::systems::voice_xp`]
+	VoiceSession {
+		user: UserId,
+
+		/// When the stay in the channel started, it can span several events
+		started: Timestamp,
+
+		/// Minutes that counted towards XP, since the last event
+		active_minutes: u64,
+	},
+	/// A user's `/daily` claim, the item is rolled from `seed`, see
+	/// [`crate::data::daily::DailyReward`]
+	DailyClaim {
+		user: UserId,
+		seed: Seed,
+	},
+	/// One member thanking another, see [`crate::data::reputation`]
+	GiveReputation {
+		giver: UserId,
+		receiver: UserId,
+		source: RepSource,
 	},
 	AdminGive {
 		user: UserId,
@@ -49,74 +102,253 @@ pub enum DBEvent {
 		id: ServerConfigRoleId,
 		discord_id: RoleId,
 	},
+	/// Archives everyone's standings and starts a new season from level 1,
+	/// `rewards[n]` goes to whoever came `n + 1`th
+	EndSeason {
+		rewards: Vec<InventoryItem>,
+	},
+	/// Gives a role, named after `name`, to everyone that reaches `level`
+	LevelRewardAdd {
+		server: GuildId,
+		level: u64,
+		name: String,
+	},
+	LevelRewardForget {
+		server: GuildId,
+		level: u64,
+	},
+	/// Changes a guild's leveling curves and recomputes everyone's level
+	SetLeveling {
+		server: GuildId,
+		leveling: Leveling,
+	},
+	/// Changes where and how a guild announces level-ups
+	SetAnnouncements {
+		server: GuildId,
+		config: AnnouncementConfig,
+	},
+	/// Whether a user gets pinged when their level-ups are announced
+	SetLevelUpPings {
+		user: UserId,
+		enabled: bool,
+	},
+	/// Changes how a guild limits XP from messages
+	SetAntiSpam {
+		server: GuildId,
+		config: AntiSpamConfig,
+	},
+	/// Makes a guild use the global profile instead of its own progress
+	SetGlobalProfile {
+		server: GuildId,
+		enabled: bool,
+	},
+	/// Replaces the whole state with one exported from somewhere else, see
+	/// [`crate::data::Database::import`]
+	Import {
+		#[serde(deserialize_with = "deserialize_boxed_state")]
+		state: Box<DBState>,
+	},
+	/// Cancels the event at `index` in the timeline, the event itself is kept
+	/// but [`crate::data::Database`] replays as if it never happened
+	Revert {
+		index: usize,
+	},
 }
 
-#[derive(Debug)]
-pub enum SideChannel {
-	CoinFlip { success: bool },
-	AdminBurnFail { user_error: DBUserError },
-	None,
+fn deserialize_boxed_state<'de, D: Deserializer<'de>>(
+	deserializer: D,
+) -> Result<Box<DBState>, D::Error> {
+	schema::deserialize_state(deserializer).map(Box::new)
 }
 
-impl DBEvent {
-	pub fn reduce_state(&self, state: DBState) -> (DBState, SideChannel) {
-		match self {
+/// Why an event couldn't be applied, [`crate::data::Database::add`] doesn't
+/// add events that fail
+#[derive(Error, Debug)]
+pub enum ReduceError {
+	#[error(transparent)]
+	User(#[from] DBUserError),
+
+	#[error("coin flip or dice roll doesn't use the seed that was committed to")]
+	UncommittedSeed,
+
+	#[error("the daily reward was already claimed today")]
+	AlreadyClaimedDaily,
+
+	#[error(transparent)]
+	Reputation(#[from] RepError),
+}
+
+impl EventEnvelope {
+	pub fn reduce_state(&self, state: &DBState) -> Result<DBState, ReduceError> {
+		match &self.event {
 			DBEvent::Counter { user } => state.mutated(|s| {
-				s.counter += 1;
-				s.people_who_counted.insert(*user);
+				let progress = s.progress_mut(self.meta.guild);
+
+				progress.counter += 1;
+				progress.people_who_counted.insert(*user);
 
-				SideChannel::None
+				Ok(())
 			}),
-			DBEvent::CoinFlip { chance } => state.mutated(|s| {
-				if chance.eval(0.5) {
-					s.flips_in_a_row += 1
+			DBEvent::CoinFlip { flip } => state.mutated(|s| {
+				let progress = s.progress_mut(self.meta.guild);
+
+				if let (Flip::Fair(fair), Some(actor)) = (flip, self.meta.actor) {
+					let mut db_user = progress.get_user_or_create(&actor);
+
+					// Each flip has to use the seed the last one committed to
+					if db_user.next_flip_seed.is_some_and(|x| x != fair.seed) {
+						return Err(ReduceError::UncommittedSeed);
+					}
+
+					db_user.next_flip_seed = Some(fair.next);
+
+					progress.update_user(&actor, db_user);
+				}
+
+				if flip.heads() {
+					progress.flips_in_a_row += 1
 				} else {
-					s.flips_in_a_row = 0
+					progress.flips_in_a_row = 0
 				}
 
-				SideChannel::CoinFlip {
-					success: chance.eval(0.5),
+				Ok(())
+			}),
+			DBEvent::DiceRoll {
+				user, seed, next, ..
+			} => state.mutated(|s| {
+				let progress = s.progress_mut(self.meta.guild);
+				let mut db_user = progress.get_user_or_create(user);
+
+				if db_user.next_roll_seed.is_some_and(|x| x != *seed) {
+					return Err(ReduceError::UncommittedSeed);
 				}
+
+				db_user.next_roll_seed = Some(*next);
+
+				progress.update_user(user, db_user);
+
+				Ok(())
 			}),
-			DBEvent::UserSendMessage { user, length } => state.mutated(|s| {
-				if *user == s.last_typed_user {
-					return SideChannel::None;
+			DBEvent::UserSendMessage {
+				user,
+				length,
+				fingerprint,
+				legacy_rules,
+			} => state.mutated(|s| {
+				let config = s.anti_spam(self.meta.guild);
+				let leveling = s.leveling(self.meta.guild);
+				let progress = s.progress_mut(self.meta.guild);
+
+				if *legacy_rules && *user == progress.last_typed_user {
+					return Ok(());
 				}
 
-				s.last_typed_user = *user;
+				progress.last_typed_user = *user;
 
 				// Figure out how much XP we need
-				let xp = calculate_length_to_xp(length);
+				let mut xp = leveling.message.xp(*length);
+
+				if !*legacy_rules {
+					xp = progress.xp_activity.entry(*user).or_default().grant(
+						&config,
+						&self.meta,
+						*fingerprint,
+						xp,
+					);
+				}
 
 				// And give it to the user
-				let mut db_user = s.get_user_or_create(user);
+				let mut db_user = progress.get_user_or_create(user);
+
+				db_user.gain_xp_at(xp, &leveling.curve, &self.meta.timestamp);
+
+				progress.update_user(user, db_user);
+
+				Ok(())
+			}),
+			DBEvent::VoiceSession {
+				user,
+				active_minutes,
+				..
+			} => state.mutated(|s| {
+				let leveling = s.leveling(self.meta.guild);
+				let progress = s.progress_mut(self.meta.guild);
+
+				let mut db_user = progress.get_user_or_create(user);
+
+				db_user.gain_xp_at(
+					active_minutes.saturating_mul(leveling.voice_per_minute),
+					&leveling.curve,
+					&self.meta.timestamp,
+				);
+
+				progress.update_user(user, db_user);
+
+				Ok(())
+			}),
+			DBEvent::DailyClaim { user, seed } => state.mutated(|s| {
+				let leveling = s.leveling(self.meta.guild);
+				let progress = s.progress_mut(self.meta.guild);
+
+				let mut db_user = progress.get_user_or_create(user);
+
+				// The day comes from the event, so replays land on the same one
+				if db_user.daily.claimed_on(&self.meta.timestamp) {
+					return Err(ReduceError::AlreadyClaimedDaily);
+				}
 
-				db_user.gain_xp(xp);
+				let streak = db_user.daily.claim(&self.meta.timestamp);
+				let reward = DailyReward::roll(streak, seed);
 
-				s.update_user(user, db_user);
+				db_user.gain_xp_at(reward.xp, &leveling.curve, &self.meta.timestamp);
+				db_user.give_item(reward.item);
 
-				SideChannel::None
+				progress.update_user(user, db_user);
+
+				Ok(())
+			}),
+			DBEvent::GiveReputation {
+				giver, receiver, ..
+			} => state.mutated(|s| {
+				let progress = s.progress_mut(self.meta.guild);
+
+				let mut db_giver = progress.get_user_or_create(giver);
+
+				db_giver
+					.rep_giving
+					.give(giver, receiver, &self.meta.timestamp)?;
+
+				progress.update_user(giver, db_giver);
+
+				let mut db_receiver = progress.get_user_or_create(receiver);
+
+				db_receiver.reputation += 1;
+
+				progress.update_user(receiver, db_receiver);
+
+				Ok(())
 			}),
 			DBEvent::AdminGive { user, item } => state.mutated(|s| {
-				let mut db_user = s.get_user_or_create(user);
+				let progress = s.progress_mut(self.meta.guild);
+
+				let mut db_user = progress.get_user_or_create(user);
 
 				db_user.give_item(*item);
 
-				s.update_user(user, db_user);
+				progress.update_user(user, db_user);
 
-				SideChannel::None
+				Ok(())
 			}),
 			DBEvent::AdminBurn { user, item } => state.mutated(|s| {
-				let mut db_user = s.get_user_or_create(user);
+				let progress = s.progress_mut(self.meta.guild);
 
-				let result = db_user.drop_item(*item);
+				let mut db_user = progress.get_user_or_create(user);
 
-				s.update_user(user, db_user);
+				db_user.drop_item(*item)?;
 
-				match result {
-					Ok(_) => SideChannel::None,
-					Err(err) => SideChannel::AdminBurnFail { user_error: err },
-				}
+				progress.update_user(user, db_user);
+
+				Ok(())
 			}),
 			DBEvent::ChannelAdd {
 				server,
@@ -129,7 +361,7 @@ impl DBEvent {
 
 				s.update_server(server, db_server);
 
-				SideChannel::None
+				Ok(())
 			}),
 			DBEvent::ChannelForget { server, id } => state.mutated(|s| {
 				let mut db_server = s.get_server_or_create(server);
@@ -138,7 +370,7 @@ impl DBEvent {
 
 				s.update_server(server, db_server);
 
-				SideChannel::None
+				Ok(())
 			}),
 			DBEvent::RoleAdd {
 				server,
@@ -151,7 +383,7 @@ impl DBEvent {
 
 				s.update_server(server, db_server);
 
-				SideChannel::None
+				Ok(())
 			}),
 			DBEvent::RoleForget { server, id } => state.mutated(|s| {
 				let mut db_server = s.get_server_or_create(server);
@@ -160,32 +392,222 @@ impl DBEvent {
 
 				s.update_server(server, db_server);
 
-				SideChannel::None
+				Ok(())
+			}),
+			DBEvent::EndSeason { rewards } => state.mutated(|s| {
+				let leveling = s.leveling(self.meta.guild);
+				let progress = s.progress_mut(self.meta.guild);
+
+				let standings = progress
+					.standings()
+					.into_iter()
+					.filter(|(_, user)| user.season_xp() > 0)
+					.map(|(id, user)| SeasonStanding {
+						user: id,
+						level: user.level,
+						this_levels_xp: user.this_levels_xp,
+						xp: user.season_xp(),
+					})
+					.collect::<Vec<_>>();
+
+				for (standing, item) in standings.iter().zip(rewards) {
+					let mut db_user = progress.get_user_or_create(&standing.user);
+
+					db_user.give_item(*item);
+
+					progress.update_user(&standing.user, db_user);
+				}
+
+				for user in progress.users.values_mut() {
+					user.start_season(&leveling.curve);
+				}
+
+				progress.seasons.push(SeasonRecord {
+					number: progress.season(),
+					ended: self.meta.timestamp,
+					standings,
+				});
+
+				Ok(())
+			}),
+			DBEvent::LevelRewardAdd {
+				server,
+				level,
+				name,
+			} => state.mutated(|s| {
+				let mut db_server = s.get_server_or_create(server);
+
+				db_server.level_rewards.insert(*level, name.clone());
+
+				s.update_server(server, db_server);
+
+				Ok(())
+			}),
+			DBEvent::LevelRewardForget { server, level } => state.mutated(|s| {
+				let mut db_server = s.get_server_or_create(server);
+
+				db_server.level_rewards.remove(level);
+
+				s.update_server(server, db_server);
+
+				Ok(())
 			}),
+			DBEvent::SetLeveling { server, leveling } => state.mutated(|s| {
+				let mut db_server = s.get_server_or_create(server);
+
+				db_server.leveling = leveling.clone();
+
+				// Guilds on the global profile keep the default curve
+				if !db_server.global_profile {
+					for user in db_server.progress.users.values_mut() {
+						user.recompute_level(&leveling.curve);
+					}
+				}
+
+				s.update_server(server, db_server);
+
+				Ok(())
+			}),
+			DBEvent::SetAnnouncements { server, config } => state.mutated(|s| {
+				let mut db_server = s.get_server_or_create(server);
+
+				db_server.announcements = config.clone();
+
+				s.update_server(server, db_server);
+
+				Ok(())
+			}),
+			DBEvent::SetLevelUpPings { user, enabled } => state.mutated(|s| {
+				if *enabled {
+					s.unpinged_users.remove(user);
+				} else {
+					s.unpinged_users.insert(*user);
+				}
+
+				Ok(())
+			}),
+			DBEvent::SetAntiSpam { server, config } => state.mutated(|s| {
+				let mut db_server = s.get_server_or_create(server);
+
+				db_server.anti_spam = config.clone();
+
+				s.update_server(server, db_server);
+
+				Ok(())
+			}),
+			DBEvent::SetGlobalProfile { server, enabled } => state.mutated(|s| {
+				let mut db_server = s.get_server_or_create(server);
+
+				// The guild's curve may have changed while its progress was unused
+				if db_server.global_profile && !*enabled {
+					for user in db_server.progress.users.values_mut() {
+						user.recompute_level(&db_server.leveling.curve);
+					}
+				}
+
+				db_server.global_profile = *enabled;
+
+				s.update_server(server, db_server);
+
+				Ok(())
+			}),
+			DBEvent::Import { state: imported } => Ok((**imported).clone()),
+			DBEvent::Revert { .. } => Ok(state.clone()),
 		}
 	}
 }
 
-#[derive(Clone, Default, Debug)]
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
 pub struct DBState {
+	/// Progress for guilds in global profile mode, and for events that didn't
+	/// happen in a guild
+	pub global: DBProgress,
+
+	pub servers: HashMap<GuildId, DBServer>,
+
+	/// Users that don't want to be pinged about level-ups, anywhere
+	#[serde(default)]
+	pub unpinged_users: BTreeSet<UserId>,
+}
+
+/// Everything players build up by using the bot, kept separately per guild
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub struct DBProgress {
 	pub counter: u64,
-	pub people_who_counted: HashSet<UserId>,
+
+	/// Sets in the state are ordered, so equal states serialize the same
+	pub people_who_counted: BTreeSet<UserId>,
 
 	pub flips_in_a_row: u32,
 
 	pub users: HashMap<UserId, DBUser>,
-	pub servers: HashMap<GuildId, DBServer>,
 
 	pub last_typed_user: UserId,
+
+	#[serde(default)]
+	pub xp_activity: HashMap<UserId, XpActivity>,
+
+	/// Seasons that have ended, oldest first
+	#[serde(default)]
+	pub seasons: Vec<SeasonRecord>,
 }
 
-#[derive(Clone, Default, Debug)]
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
 pub struct DBServer {
 	pub channels: HashMap<ServerConfigChannelId, ChannelId>,
 	pub roles: HashMap<ServerConfigRoleId, RoleId>,
+
+	#[serde(default)]
+	pub progress: DBProgress,
+
+	/// Use [`DBState::global`] instead of `progress`
+	#[serde(default)]
+	pub global_profile: bool,
+
+	#[serde(default)]
+	pub anti_spam: AntiSpamConfig,
+
+	#[serde(default)]
+	pub leveling: Leveling,
+
+	/// Names of the roles given at each level, the roles themselves are made
+	/// by autoconfig
+	#[serde(default)]
+	pub level_rewards: BTreeMap<u64, String>,
+
+	#[serde(default)]
+	pub announcements: AnnouncementConfig,
 }
 
-impl DBState {
+impl DBServer {
+	/// Reward roles for `levels` that autoconfig has already made
+	pub fn level_reward_roles(&self, levels: impl RangeBounds<u64>) -> Vec<RoleId> {
+		self.level_rewards
+			.range(levels)
+			.filter_map(|(level, _)| self.roles.get(&level_reward_role(*level)).copied())
+			.collect()
+	}
+}
+
+static NO_PROGRESS: LazyLock<DBProgress> = LazyLock::new(DBProgress::default);
+
+impl DBProgress {
+	/// The number of the season that's running
+	pub fn season(&self) -> usize { self.seasons.len() + 1 }
+
+	/// Everyone by level and then XP this season, best first
+	pub fn standings(&self) -> Vec<(UserId, DBUser)> {
+		let mut users = self
+			.users
+			.iter()
+			.map(|(id, user)| (*id, user.clone()))
+			.collect::<Vec<_>>();
+
+		users.sort_by_key(|(id, x)| (Reverse(x.level), Reverse(x.this_levels_xp), *id));
+
+		users
+	}
+
 	pub fn get_user_or_default(&self, id: &UserId) -> DBUser {
 		if self.users.contains_key(id) {
 			self.users[id].clone()
@@ -203,6 +625,49 @@ impl DBState {
 	}
 
 	pub fn update_user(&mut self, id: &UserId, user: DBUser) { self.users.insert(*id, user); }
+}
+
+impl DBState {
+	/// The guild whose progress is used for events in `guild`, `None` meaning
+	/// the global profile
+	pub fn progress_owner(&self, guild: Option<GuildId>) -> Option<GuildId> {
+		guild.filter(|id| !self.servers.get(id).is_some_and(|x| x.global_profile))
+	}
+
+	pub fn progress(&self, guild: Option<GuildId>) -> &DBProgress {
+		match self.progress_owner(guild) {
+			Some(id) => self.servers.get(&id).map_or(&NO_PROGRESS, |x| &x.progress),
+			None => &self.global,
+		}
+	}
+
+	pub fn progress_mut(&mut self, guild: Option<GuildId>) -> &mut DBProgress {
+		match self.progress_owner(guild) {
+			Some(id) => &mut self.servers.entry(id).or_default().progress,
+			None => &mut self.global,
+		}
+	}
+
+	/// How messages in `guild` are limited, DMs use the defaults
+	pub fn anti_spam(&self, guild: Option<GuildId>) -> AntiSpamConfig {
+		guild
+			.and_then(|id| self.servers.get(&id))
+			.map(|x| x.anti_spam.clone())
+			.unwrap_or_default()
+	}
+
+	/// The curves for whoever's progress `guild` uses, the global profile
+	/// always uses the defaults
+	pub fn leveling(&self, guild: Option<GuildId>) -> Leveling {
+		self.progress_owner(guild)
+			.and_then(|id| self.servers.get(&id))
+			.map(|x| x.leveling.clone())
+			.unwrap_or_default()
+	}
+
+	pub fn get_user_or_default(&self, guild: Option<GuildId>, id: &UserId) -> DBUser {
+		self.progress(guild).get_user_or_default(id)
+	}
 
 	pub fn get_server_or_default(&self, id: &GuildId) -> DBServer {
 		if self.servers.contains_key(id) {
@@ -226,14 +691,14 @@ impl DBState {
 }
 
 impl DBState {
-	pub fn mutated<T>(&self, callback: T) -> (Self, SideChannel)
+	pub fn mutated<T>(&self, callback: T) -> Result<Self, ReduceError>
 	where
-		T: Fn(&mut Self) -> SideChannel,
+		T: FnOnce(&mut Self) -> Result<(), ReduceError>,
 	{
 		let mut fork = self.clone();
 
-		let side_channel = callback(&mut fork);
+		callback(&mut fork)?;
 
-		(fork, side_channel)
+		Ok(fork)
 	}
 }
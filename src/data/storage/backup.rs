@@ -0,0 +1,135 @@
+use crate::data::storage::{write_atomic, StorageBackend};
+use eyre::{OptionExt, Result};
+use serenity::all::Timestamp;
+use std::{
+	fs::File,
+	path::{Path, PathBuf},
+};
+
+/// Makes a new backup of the database at `path`, `fill` is given an empty
+/// directory to copy the database's files into. Only the newest `keep`
+/// backups are kept
+///
+/// The backup is filled under a temporary name first, so a backup that shows
+/// up in [`list`] is always complete.
+pub fn create<T>(path: &Path, keep: usize, fill: T) -> Result<PathBuf>
+where
+	T: FnOnce(&Path) -> Result<()>,
+{
+	let backups = backup_dir(path)?;
+	let mut name = timestamped_name();
+
+	// Backups made in quick succession can get the same timestamp, the suffix
+	// keeps them sorted
+	for n in 1.. {
+		if !backups.join(&name).exists() {
+			break;
+		}
+
+		name = format!("{}-{n}", timestamped_name());
+	}
+
+	let temp = backups.join(format!("{name}.tmp"));
+	let done = backups.join(name);
+
+	std::fs::create_dir_all(&temp)?;
+
+	fill(&temp)?;
+
+	for entry in std::fs::read_dir(&temp)? {
+		File::open(entry?.path())?.sync_all()?;
+	}
+
+	std::fs::rename(&temp, &done)?;
+	sync_dir(&backups)?;
+
+	for old in list(path)?.into_iter().skip(keep) {
+		std::fs::remove_dir_all(old)?;
+	}
+
+	Ok(done)
+}
+
+/// Every complete backup of the database at `path`, newest first
+pub fn list(path: &Path) -> Result<Vec<PathBuf>> {
+	let backups = backup_dir(path)?;
+
+	if !backups.exists() {
+		return Ok(vec![]);
+	}
+
+	let mut found = vec![];
+
+	for entry in std::fs::read_dir(backups)? {
+		let entry = entry?.path();
+
+		if entry.is_dir() && entry.extension().is_none_or(|x| x != "tmp") {
+			found.push(entry);
+		}
+	}
+
+	// The names are timestamps, so they sort by age
+	found.sort();
+	found.reverse();
+
+	Ok(found)
+}
+
+/// Moves the files of the database at `path` into a new directory next to it,
+/// so it can be inspected later or put back with [`restore`]
+pub fn set_aside(backend: StorageBackend, path: &Path) -> Result<PathBuf> {
+	let aside = sibling(path, &format!(".corrupt-{}", timestamped_name()))?;
+
+	std::fs::create_dir_all(&aside)?;
+
+	for file in backend.files(path)? {
+		if file.exists() {
+			std::fs::rename(&file, aside.join(file_name(&file)?))?;
+		}
+	}
+
+	Ok(aside)
+}
+
+/// Replaces the database at `path` with the one in `backup`
+pub fn restore(backend: StorageBackend, path: &Path, backup: &Path) -> Result<()> {
+	for file in backend.files(path)? {
+		let saved = backup.join(file_name(&file)?);
+
+		if saved.exists() {
+			write_atomic(&file, &std::fs::read(saved)?)?;
+		} else if file.exists() {
+			std::fs::remove_file(file)?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Makes a rename in `dir` survive a crash
+pub fn sync_dir(dir: &Path) -> Result<()> {
+	// Directories can't be opened like this on Windows, where renames don't
+	// need it anyway
+	#[cfg(unix)]
+	File::open(dir)?.sync_all()?;
+
+	#[cfg(not(unix))]
+	let _ = dir;
+
+	Ok(())
+}
+
+fn backup_dir(path: &Path) -> Result<PathBuf> { sibling(path, ".backups") }
+
+/// The current time in a form that's safe in file names and sorts by age
+fn timestamped_name() -> String { Timestamp::now().to_string().replace(':', "-") }
+
+fn file_name(path: &Path) -> Result<&std::ffi::OsStr> {
+	path.file_name().ok_or_eyre("Invalid path")
+}
+
+fn sibling(path: &Path, suffix: &str) -> Result<PathBuf> {
+	Ok(PathBuf::from(
+		path.to_str().ok_or_eyre("Invalid path")?.to_string() + suffix,
+	))
+}
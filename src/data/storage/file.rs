@@ -0,0 +1,120 @@
+use crate::data::{
+	envelope::EventEnvelope,
+	journal,
+	journal::Journal,
+	snapshot::Snapshot,
+	storage::{backup, Storage, StorageBackend},
+};
+use eyre::{OptionExt, Result};
+use std::path::{Path, PathBuf};
+
+/// Keeps the timeline in a [`Journal`], with the snapshot and archives as
+/// files next to it
+#[derive(Debug)]
+pub struct FileStorage {
+	journal: Journal,
+}
+
+impl FileStorage {
+	pub fn open(path: &Path) -> Result<Self> {
+		let legacy = path.with_extension("json");
+
+		if !path.exists() && legacy.exists() {
+			println!("info: migrating {} to {}", legacy.display(), path.display());
+
+			journal::migrate_from_json(&legacy, path)?;
+
+			let legacy_snapshot = sibling(&legacy, ".snapshot")?;
+
+			if legacy_snapshot.exists() {
+				std::fs::rename(legacy_snapshot, sibling(path, ".snapshot")?)?;
+			}
+
+			std::fs::rename(&legacy, sibling(&legacy, ".migrated")?)?;
+		}
+
+		Ok(Self {
+			journal: Journal::open(path)?,
+		})
+	}
+
+	/// Opens the journal at `path` without migrating or repairing anything
+	pub fn open_read_only(path: &Path) -> Result<Self> {
+		Ok(Self {
+			journal: Journal::open_read_only(path)?,
+		})
+	}
+
+	fn sibling(&self, suffix: &str) -> Result<PathBuf> { sibling(self.journal.path(), suffix) }
+}
+
+impl Storage for FileStorage {
+	fn load_events(&mut self) -> Result<Vec<EventEnvelope>> { self.journal.read() }
+
+	fn append_event(&mut self, event: &EventEnvelope) -> Result<()> { self.journal.append(event) }
+
+	fn load_compacted(&mut self) -> Result<Option<usize>> {
+		match self.journal.starts_after()? {
+			Some(x) => Ok(Some(x)),
+			// Journals compacted before they had a header can't tell
+			None if self.sibling(".base")?.exists() => Ok(None),
+			None => Ok(Some(0)),
+		}
+	}
+
+	fn load_snapshot(&mut self) -> Result<Option<Snapshot>> {
+		Snapshot::load(&self.sibling(".snapshot")?)
+	}
+
+	fn store_snapshot(&mut self, snapshot: &Snapshot) -> Result<()> {
+		snapshot.store(&self.sibling(".snapshot")?)
+	}
+
+	fn load_compaction_snapshot(&mut self) -> Result<Option<Snapshot>> {
+		Snapshot::load(&self.sibling(".base")?)
+	}
+
+	fn compact(&mut self, snapshot: &Snapshot) -> Result<String> {
+		let events = self.journal.read()?;
+
+		let archive_path = self.sibling(&format!(
+			".archive-{}-{}",
+			snapshot.compacted - events.len(),
+			snapshot.compacted
+		))?;
+
+		journal::write(&archive_path, &events)?;
+
+		// Empty the journal before writing the snapshots, it says where it starts
+		// so if we crash in between the stale snapshot no longer fits and
+		// loading fails loudly, instead of replaying from the wrong state
+		self.journal.rewrite(snapshot.compacted, &[])?;
+
+		// Periodic snapshots will replace the one below, so keep a copy of where
+		// the timeline starts from
+		snapshot.store(&self.sibling(".base")?)?;
+		self.store_snapshot(snapshot)?;
+
+		Ok(archive_path.display().to_string())
+	}
+
+	fn backup(&mut self, keep: usize) -> Result<PathBuf> {
+		let path = self.journal.path().to_path_buf();
+
+		backup::create(&path, keep, |dir| {
+			for file in StorageBackend::Journal.files(&path)? {
+				if let Some(name) = file.file_name().filter(|_| file.exists()) {
+					std::fs::copy(&file, dir.join(name))?;
+				}
+			}
+
+			Ok(())
+		})
+	}
+}
+
+fn sibling(path: &Path, suffix: &str) -> Result<PathBuf> {
+	Ok(PathBuf::from(
+		path.to_str().ok_or_eyre("Invalid path")?.to_string() + suffix,
+	))
+}
@@ -0,0 +1,120 @@
+use crate::data::{envelope::EventEnvelope, snapshot::Snapshot};
+use eyre::{OptionExt, Result};
+use std::{
+	fmt::Debug,
+	fs::File,
+	io::Write,
+	path::{Path, PathBuf},
+};
+
+pub mod backup;
+pub mod file;
+pub mod sqlite;
+
+/// Somewhere the timeline and its snapshots can be kept
+pub trait Storage: Debug + Send {
+	/// Every event that hasn't been compacted away, oldest first
+	fn load_events(&mut self) -> Result<Vec<EventEnvelope>>;
+
+	fn append_event(&mut self, event: &EventEnvelope) -> Result<()>;
+
+	/// How many events were archived before the ones [`Storage::load_events`]
+	/// gives back, `None` if the storage can't tell
+	fn load_compacted(&mut self) -> Result<Option<usize>>;
+
+	/// The most recent snapshot, if one was ever stored
+	fn load_snapshot(&mut self) -> Result<Option<Snapshot>>;
+
+	fn store_snapshot(&mut self, snapshot: &Snapshot) -> Result<()>;
+
+	/// The snapshot stored by the last [`Storage::compact`], which is the state
+	/// the live timeline starts from
+	fn load_compaction_snapshot(&mut self) -> Result<Option<Snapshot>>;
+
+	/// Moves every event out of the live timeline into an archive and stores
+	/// `snapshot` in their place, returns a description of where the archive
+	/// went
+	fn compact(&mut self, snapshot: &Snapshot) -> Result<String>;
+
+	/// Copies the database into a new backup, see [`backup::create`]
+	fn backup(&mut self, keep: usize) -> Result<PathBuf>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageBackend {
+	/// An append-only journal file, see [`file::FileStorage`]
+	Journal,
+
+	/// An embedded SQLite database, see [`sqlite::SqliteStorage`]
+	Sqlite,
+}
+
+impl StorageBackend {
+	/// Where the bot keeps this backend's database
+	pub fn default_path(&self) -> &'static Path {
+		match self {
+			StorageBackend::Journal => "./db.jsonl".as_ref(),
+			StorageBackend::Sqlite => "./db.sqlite".as_ref(),
+		}
+	}
+
+	/// Every file the database at `path` can be made of
+	pub fn files(&self, path: &Path) -> Result<Vec<PathBuf>> {
+		let with_suffix = |suffix: &str| -> Result<PathBuf> {
+			Ok(PathBuf::from(
+				path.to_str().ok_or_eyre("Invalid path")?.to_string() + suffix,
+			))
+		};
+
+		Ok(match self {
+			StorageBackend::Journal => vec![
+				path.to_path_buf(),
+				with_suffix(".snapshot")?,
+				with_suffix(".base")?,
+			],
+			StorageBackend::Sqlite => vec![
+				path.to_path_buf(),
+				with_suffix("-wal")?,
+				with_suffix("-shm")?,
+			],
+		})
+	}
+
+	pub fn open_at(&self, path: &Path) -> Result<Box<dyn Storage>> {
+		Ok(match self {
+			StorageBackend::Journal => Box::new(file::FileStorage::open(path)?),
+			StorageBackend::Sqlite => Box::new(sqlite::SqliteStorage::open(path)?),
+		})
+	}
+
+	/// Like [`StorageBackend::open_at`], but nothing is migrated, repaired or
+	/// created, to go with [`crate::data::Database::inspect`]
+	pub fn open_read_only(&self, path: &Path) -> Result<Box<dyn Storage>> {
+		Ok(match self {
+			StorageBackend::Journal => Box::new(file::FileStorage::open_read_only(path)?),
+			StorageBackend::Sqlite => Box::new(sqlite::SqliteStorage::open_read_only(path)?),
+		})
+	}
+}
+
+/// Replaces the file at `path` with `contents`, without ever leaving it half
+/// written
+///
+/// The contents go to a temporary file first, which is synced and then renamed
+/// over `path`.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+	let temp = PathBuf::from(path.to_str().ok_or_eyre("Invalid path")?.to_string() + ".tmp");
+
+	let mut file = File::create(&temp)?;
+
+	file.write_all(contents)?;
+	file.sync_all()?;
+
+	std::fs::rename(&temp, path)?;
+
+	let parent = path.parent().filter(|x| !x.as_os_str().is_empty());
+
+	backup::sync_dir(parent.unwrap_or(Path::new(".")))?;
+
+	Ok(())
+}
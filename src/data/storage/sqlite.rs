@@ -0,0 +1,212 @@
+use crate::data::{
+	envelope::EventEnvelope,
+	schema,
+	snapshot::Snapshot,
+	storage::{backup, Storage},
+};
+use eyre::{OptionExt, Result};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use std::path::{Path, PathBuf};
+
+/// Keeps the timeline in an embedded SQLite database
+///
+/// Events are stored as JSON, one row each, keyed by their position in the
+/// timeline. So history can be queried with something like:
+///
+/// ```sql
+/// SELECT idx, event FROM events WHERE json_extract(event, '$.event.AdminGive') IS NOT NULL;
+/// ```
+#[derive(Debug)]
+pub struct SqliteStorage {
+	path: PathBuf,
+	connection: Connection,
+}
+
+impl SqliteStorage {
+	pub fn open(path: &Path) -> Result<Self> {
+		let connection = Connection::open(path)?;
+
+		connection.execute_batch(
+			"PRAGMA journal_mode = WAL;
+			PRAGMA synchronous = FULL;
+
+			CREATE TABLE IF NOT EXISTS events (
+				idx INTEGER PRIMARY KEY,
+				event TEXT NOT NULL
+			);
+
+			CREATE TABLE IF NOT EXISTS archived_events (
+				idx INTEGER PRIMARY KEY,
+				event TEXT NOT NULL
+			);
+
+			CREATE TABLE IF NOT EXISTS snapshots (
+				offset INTEGER PRIMARY KEY,
+				compacted INTEGER NOT NULL,
+				state TEXT NOT NULL
+			);",
+		)?;
+
+		Ok(Self {
+			path: path.to_path_buf(),
+			connection,
+		})
+	}
+
+	/// Opens the database at `path` without creating or changing anything
+	pub fn open_read_only(path: &Path) -> Result<Self> {
+		Ok(Self {
+			path: path.to_path_buf(),
+			connection: Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?,
+		})
+	}
+
+	fn next_index(&self) -> Result<i64> {
+		Ok(self.connection.query_row(
+			"SELECT MAX(
+				(SELECT COALESCE(MAX(idx) + 1, 0) FROM events),
+				(SELECT COALESCE(MAX(idx) + 1, 0) FROM archived_events)
+			)",
+			[],
+			|row| row.get(0),
+		)?)
+	}
+}
+
+impl Storage for SqliteStorage {
+	fn load_events(&mut self) -> Result<Vec<EventEnvelope>> {
+		let mut statement = self
+			.connection
+			.prepare("SELECT event FROM events ORDER BY idx")?;
+
+		let rows = statement.query_map([], |row| row.get::<_, String>(0))?;
+
+		let mut events = vec![];
+
+		for row in rows {
+			events.push(schema::decode(row?.as_bytes())?);
+		}
+
+		Ok(events)
+	}
+
+	fn append_event(&mut self, event: &EventEnvelope) -> Result<()> {
+		let idx = self.next_index()?;
+
+		self.connection.execute(
+			"INSERT INTO events (idx, event) VALUES (?1, ?2)",
+			params![idx, schema::encode(event)?],
+		)?;
+
+		Ok(())
+	}
+
+	fn load_compacted(&mut self) -> Result<Option<usize>> {
+		let archived: i64 =
+			self.connection
+				.query_row("SELECT COUNT(*) FROM archived_events", [], |row| row.get(0))?;
+
+		Ok(Some(archived as usize))
+	}
+
+	fn load_snapshot(&mut self) -> Result<Option<Snapshot>> {
+		select_snapshot(
+			&self.connection,
+			"SELECT offset, compacted, state FROM snapshots ORDER BY offset DESC LIMIT 1",
+		)
+	}
+
+	fn store_snapshot(&mut self, snapshot: &Snapshot) -> Result<()> {
+		let transaction = self.connection.transaction()?;
+
+		insert_snapshot(&transaction, snapshot)?;
+
+		transaction.commit()?;
+
+		Ok(())
+	}
+
+	fn load_compaction_snapshot(&mut self) -> Result<Option<Snapshot>> {
+		// Only a compaction leaves a snapshot with no events after it
+		select_snapshot(
+			&self.connection,
+			"SELECT offset, compacted, state FROM snapshots WHERE offset = compacted ORDER BY offset DESC LIMIT 1",
+		)
+	}
+
+	fn compact(&mut self, snapshot: &Snapshot) -> Result<String> {
+		let transaction = self.connection.transaction()?;
+
+		let moved = transaction.execute(
+			"INSERT INTO archived_events (idx, event) SELECT idx, event FROM events",
+			[],
+		)?;
+
+		transaction.execute("DELETE FROM events", [])?;
+
+		insert_snapshot(&transaction, snapshot)?;
+
+		transaction.commit()?;
+
+		Ok(format!("the archived_events table ({moved} rows)"))
+	}
+
+	fn backup(&mut self, keep: usize) -> Result<PathBuf> {
+		backup::create(&self.path, keep, |dir| {
+			let name = self.path.file_name().ok_or_eyre("Invalid path")?;
+
+			// Unlike copying the file, this includes whatever is still in the WAL
+			self.connection.execute(
+				"VACUUM INTO ?1",
+				params![dir.join(name).to_str().ok_or_eyre("Invalid path")?],
+			)?;
+
+			Ok(())
+		})
+	}
+}
+
+fn insert_snapshot(connection: &Connection, snapshot: &Snapshot) -> Result<()> {
+	connection.execute(
+		"INSERT OR REPLACE INTO snapshots (offset, compacted, state) VALUES (?1, ?2, ?3)",
+		params![
+			snapshot.offset as i64,
+			snapshot.compacted as i64,
+			serde_json::to_string(&snapshot.state)?
+		],
+	)?;
+
+	// Only the newest snapshot and the one the last compaction left are ever
+	// loaded, the rest would pile up forever
+	connection.execute(
+		"DELETE FROM snapshots WHERE offset NOT IN (
+			SELECT COALESCE(MAX(offset), -1) FROM snapshots WHERE offset != compacted
+			UNION SELECT COALESCE(MAX(offset), -1) FROM snapshots WHERE offset = compacted
+		)",
+		[],
+	)?;
+
+	Ok(())
+}
+
+fn select_snapshot(connection: &Connection, query: &str) -> Result<Option<Snapshot>> {
+	let row = connection
+		.query_row(query, [], |row| {
+			Ok((
+				row.get::<_, i64>(0)?,
+				row.get::<_, i64>(1)?,
+				row.get::<_, String>(2)?,
+			))
+		})
+		.optional()?;
+
+	let Some((offset, compacted, state)) = row else {
+		return Ok(None);
+	};
+
+	Ok(Some(Snapshot {
+		offset: offset as usize,
+		compacted: compacted as usize,
+		state: schema::decode_state(serde_json::from_str(&state)?)?,
+	}))
+}
@@ -1,31 +1,68 @@
 use crate::data::{
 	battle::{Living, LivingBuilder},
+	daily::DailyStreak,
+	drawing::{canvas, display_name, draw_avatar, draw_bar, light_font, rect, WHITE},
 	items::InventoryItem,
+	leveling::{LevelCurve, MAX_LEVEL},
+	reputation::RepGiving,
+	rng::Seed,
 };
-use ab_glyph::{FontRef, PxScale};
+use ab_glyph::PxScale;
 use eyre::Result;
-use image::{
-	imageops::{overlay, FilterType},
-	GenericImage, Rgba, RgbaImage,
-};
+use image::{Rgba, RgbaImage};
 use imageproc::{
 	drawing::{draw_filled_rect_mut, draw_text_mut},
 	rect::Rect,
 };
-use serenity::all::{CreateAttachment, User};
+use serde::{Deserialize, Serialize};
+use serenity::all::{CreateAttachment, Timestamp, User};
 use std::{
 	fmt::{Display, Formatter},
 	path::Path,
 };
 use thiserror::Error;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct DBUser {
 	pub this_levels_xp: u64,
 	pub xp_until_next_level: u64,
 	pub level: u64,
 	pub items: Vec<InventoryItem>,
 	pub life: Living,
+
+	/// The seed this user's next coin flip will use, see
+	/// [`crate::data::rng::FairFlip`]
+	#[serde(default)]
+	pub next_flip_seed: Option<Seed>,
+
+	/// The seed this user's next `/roll` will use
+	#[serde(default)]
+	pub next_roll_seed: Option<Seed>,
+
+	/// All the XP this user ever gained, across every season
+	#[serde(default)]
+	pub total_xp: u64,
+
+	/// The part of `total_xp` gained in seasons that have ended
+	#[serde(default)]
+	pub past_seasons_xp: u64,
+
+	/// The [`week`] that `weekly_xp` was gained in
+	#[serde(default)]
+	pub week: i64,
+
+	#[serde(default)]
+	pub weekly_xp: u64,
+
+	#[serde(default)]
+	pub daily: DailyStreak,
+
+	/// Times other members have thanked this user
+	#[serde(default)]
+	pub reputation: u64,
+
+	#[serde(default)]
+	pub rep_giving: RepGiving,
 }
 
 impl Default for DBUser {
@@ -36,6 +73,15 @@ impl Default for DBUser {
 			level: 1,
 			items: vec![],
 			life: LivingBuilder::new().health(150).build().unwrap(),
+			next_flip_seed: None,
+			next_roll_seed: None,
+			total_xp: 0,
+			past_seasons_xp: 0,
+			week: 0,
+			weekly_xp: 0,
+			daily: DailyStreak::default(),
+			reputation: 0,
+			rep_giving: RepGiving::default(),
 		}
 	}
 }
@@ -57,29 +103,48 @@ impl Display for DBUserError {
 	}
 }
 
-macro_rules! rect {
-	($pos: expr, $size: expr) => {
-		Rect::at($pos.0 as i32, $pos.1 as i32).of_size($size.0 as u32, $size.1 as u32)
-	};
+/// Weeks since the first monday after the unix epoch, weeks start on monday
+/// at midnight UTC
+pub fn week(at: &Timestamp) -> i64 {
+	const MONDAY: i64 = 4 * 24 * 60 * 60;
+	const WEEK: i64 = 7 * 24 * 60 * 60;
+
+	(at.unix_timestamp() - MONDAY).div_euclid(WEEK)
 }
 
 impl DBUser {
-	pub fn update_required_xp(&mut self) { self.xp_until_next_level = 100 + self.level.pow(2) / 2 }
+	pub fn update_required_xp(&mut self, curve: &LevelCurve) {
+		self.xp_until_next_level = curve.required(self.level)
+	}
 
-	pub fn gain_xp(&mut self, xp: u64) {
+	pub fn gain_xp(&mut self, xp: u64, curve: &LevelCurve) {
 		if self.level < 1 {
 			self.level = 1
 		}
 
-		self.this_levels_xp += xp;
-		self.check_level_up();
+		self.this_levels_xp = self.this_levels_xp.saturating_add(xp);
+		self.total_xp = self.total_xp.saturating_add(xp);
+		self.check_level_up(curve);
 	}
 
-	pub fn check_level_up(&mut self) {
-		loop {
-			self.update_required_xp();
+	/// [`DBUser::gain_xp`], also counting it towards the week of `at`
+	pub fn gain_xp_at(&mut self, xp: u64, curve: &LevelCurve, at: &Timestamp) {
+		let week = week(at);
+
+		if self.week != week {
+			self.week = week;
+			self.weekly_xp = 0;
+		}
+
+		self.weekly_xp = self.weekly_xp.saturating_add(xp);
+		self.gain_xp(xp, curve);
+	}
 
-			if self.this_levels_xp < self.xp_until_next_level {
+	pub fn check_level_up(&mut self, curve: &LevelCurve) {
+		for required in curve.requirements(self.level) {
+			self.xp_until_next_level = required;
+
+			if self.this_levels_xp < self.xp_until_next_level || self.level >= MAX_LEVEL {
 				break;
 			}
 
@@ -89,6 +154,38 @@ impl DBUser {
 		}
 	}
 
+	/// XP gained since the current season started
+	pub fn season_xp(&self) -> u64 { self.total_xp.saturating_sub(self.past_seasons_xp) }
+
+	/// Works out the level again from [`DBUser::season_xp`]
+	pub fn recompute_level(&mut self, curve: &LevelCurve) {
+		self.level = 1;
+		self.this_levels_xp = self.season_xp();
+		self.check_level_up(curve);
+	}
+
+	/// Starts over from level 1, keeping lifetime XP and items
+	pub fn start_season(&mut self, curve: &LevelCurve) {
+		self.past_seasons_xp = self.total_xp;
+		self.recompute_level(curve);
+	}
+
+	/// Fills in [`DBUser::total_xp`] for users from before it was kept, who
+	/// all levelled up on the default curve
+	pub fn backfill_total_xp(&mut self) {
+		if self.total_xp > 0 {
+			return;
+		}
+
+		let curve = LevelCurve::default();
+
+		self.total_xp = curve
+			.requirements(1)
+			.take(self.level.saturating_sub(1) as usize)
+			.sum::<u64>()
+			+ self.this_levels_xp;
+	}
+
 	pub fn give_item(&mut self, item: InventoryItem) { self.items.push(item); }
 
 	pub fn drop_item(&mut self, item: InventoryItem) -> Result<(), DBUserError> {
@@ -105,44 +202,23 @@ impl DBUser {
 		Ok(())
 	}
 
-	pub async fn image(&self, user: &User) -> Result<RgbaImage> {
-		let size = (550, 244);
-
-		let mut img = RgbaImage::new(size.0, size.1);
-		let bg = Rgba([17, 17, 17, 255]);
-
-		for x in 0..size.0 {
-			// Fill background
-			for y in 0..size.1 {
-				*img.get_pixel_mut(x, y) = bg; // Set BG Color
-			}
-		}
+	/// This user's profile card, `season` being the one that's running
+	pub async fn image(&self, user: &User, season: usize) -> Result<RgbaImage> {
+		let mut img = canvas((550, 244));
 
-		// Get font
-		let light_bytes = std::fs::read("./fonts/light.ttf")?;
-
-		let font_light = FontRef::try_from_slice(light_bytes.as_slice())
-			.ok()
-			.ok_or(DBUserError::FontFailedToParse)?;
-
-		// Draw display name
-		let display_name = (if let Some(x) = &user.global_name {
-			x
-		} else {
-			&user.name
-		})
-		.to_uppercase();
+		let font_light = light_font()?;
 
 		let text_scale_light = 1.333f32;
 
+		// Draw display name
 		draw_text_mut(
 			&mut img,
-			Rgba([255, 255, 255, 255]),
+			WHITE,
 			224,
 			40,
 			PxScale::from(40f32 * text_scale_light),
 			&font_light,
-			&display_name,
+			&display_name(user),
 		);
 
 		let level = format!(
@@ -152,7 +228,7 @@ impl DBUser {
 
 		draw_text_mut(
 			&mut img,
-			Rgba([255, 255, 255, 255]),
+			WHITE,
 			224,
 			98,
 			PxScale::from(20f32 * text_scale_light),
@@ -160,11 +236,16 @@ impl DBUser {
 			&level,
 		);
 
-		let health = format!("{} / {} Health", self.life.health(), self.life.max_health());
+		let health = format!(
+			"{} / {} Health · {} Rep",
+			self.life.health(),
+			self.life.max_health(),
+			self.reputation
+		);
 
 		draw_text_mut(
 			&mut img,
-			Rgba([255, 255, 255, 255]),
+			WHITE,
 			224,
 			129,
 			PxScale::from(20f32 * text_scale_light),
@@ -173,43 +254,11 @@ impl DBUser {
 		);
 
 		// Leveling bar
-		let leveling_bar_pos = (224f32, 168f32);
-		let leveling_bar_size = (286f32, 30f32);
-		let filled_percentage = self.this_levels_xp as f32 / self.xp_until_next_level as f32;
-		let filled_size = (
-			if leveling_bar_size.0 * filled_percentage > 1f32 {
-				leveling_bar_size.0 * filled_percentage
-			} else {
-				1f32
-			},
-			leveling_bar_size.1,
-		);
-
-		// Fill the outline
-		draw_filled_rect_mut(
-			&mut img,
-			rect!(leveling_bar_pos, leveling_bar_size),
-			Rgba([255, 255, 255, 255]),
-		);
-
-		// Hollow out the outline
-		let outline_thickness = 1f32;
-		let outline_hole_pos = (
-			leveling_bar_pos.0 + outline_thickness,
-			leveling_bar_pos.1 + outline_thickness,
-		);
-		let outline_hole_size = (
-			leveling_bar_size.0 - 2f32 * outline_thickness,
-			leveling_bar_size.1 - 2f32 * outline_thickness,
-		);
-
-		draw_filled_rect_mut(&mut img, rect!(outline_hole_pos, outline_hole_size), bg);
-
-		// Draw the filled portion
-		draw_filled_rect_mut(
+		draw_bar(
 			&mut img,
-			rect!(leveling_bar_pos, filled_size),
-			Rgba([255, 255, 255, 255]),
+			(224f32, 168f32),
+			(286f32, 30f32),
+			self.this_levels_xp as f32 / self.xp_until_next_level as f32,
 		);
 
 		// Health bar
@@ -228,43 +277,34 @@ impl DBUser {
 			health_bar_size.1,
 		);
 
-		draw_filled_rect_mut(
-			&mut img,
-			rect!(health_bar_pos, health_filled_size),
-			Rgba([255, 255, 255, 255]),
-		);
-
-		// Render user's profile picture
-		let avatar_url = user.avatar_url().unwrap_or(user.default_avatar_url());
-		let avatar_bytes = reqwest::get(avatar_url).await?.bytes().await?;
-
-		let avatar_image = image::load_from_memory(&avatar_bytes)?;
+		draw_filled_rect_mut(&mut img, rect!(health_bar_pos, health_filled_size), WHITE);
 
-		// 1. Resize the image to be 156x156
-		let size = 164f32;
-		let mut tiny = avatar_image.resize(size as u32, size as u32, FilterType::CatmullRom);
+		let mut lifetime = format!("Season {season} · {} xp in all seasons", self.total_xp);
 
-		// 2. Go through every pixel and clip
-		for x in 0..tiny.width() {
-			for y in 0..tiny.height() {
-				let pos = (x as f32 / size, y as f32 / size);
-				let distance =
-					(1f32 - (pos.0 * 2f32)).powf(2f32) + (1f32 - (pos.1 * 2f32)).powf(2f32);
+		let streak = self.daily.current(&Timestamp::now());
 
-				if distance > 1f32 {
-					tiny.put_pixel(x, y, bg);
-				}
-			}
+		if streak > 0 {
+			lifetime += &format!(" · {streak} day streak");
 		}
 
-		// 3. Draw it at 40, 40
-		overlay(&mut img, &tiny, 40, 40);
+		draw_text_mut(
+			&mut img,
+			WHITE,
+			224,
+			212,
+			PxScale::from(12f32 * text_scale_light),
+			&font_light,
+			&lifetime,
+		);
+
+		// Render user's profile picture
+		draw_avatar(&mut img, user, (40, 40), 164f32).await?;
 
 		Ok(img)
 	}
 
-	pub async fn attachment_image(&self, user: &User) -> Result<CreateAttachment> {
-		let image = self.image(user).await?;
+	pub async fn attachment_image(&self, user: &User, season: usize) -> Result<CreateAttachment> {
+		let image = self.image(user, season).await?;
 
 		let file_path = "./temp.png";
 
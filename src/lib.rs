@@ -0,0 +1,12 @@
+use crate::data::Database;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+pub mod commands;
+pub mod config;
+pub mod data;
+pub mod systems;
+pub mod utils;
+
+pub type Error = Box<dyn std::error::Error + Send + Sync>;
+pub type Context<'a> = poise::Context<'a, Arc<Mutex<Database>>, Error>;
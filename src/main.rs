@@ -1,31 +1,34 @@
-use std::{path::Path, sync::Arc};
+use std::sync::Arc;
 
-use crate::{
+use eyre::Result;
+use poise::{builtins::create_application_commands, serenity_prelude as serenity};
+use quicksilver::{
 	commands::{
-		admin_burn::admin_burn, admin_give::admin_give, coin::coinflip, counter::counter,
-		goto::goto, inventory::inventory, status::status, test::test,
+		admin_announcements::admin_announcements, admin_anti_spam::admin_anti_spam,
+		admin_burn::admin_burn, admin_compact::admin_compact, admin_end_season::admin_end_season,
+		admin_give::admin_give, admin_global_profile::admin_global_profile,
+		admin_history::admin_history, admin_level_reward::admin_level_reward,
+		admin_leveling::admin_leveling, admin_revert::admin_revert, coin::coinflip,
+		counter::counter, daily::daily, goto::goto, inventory::inventory, leaderboard::leaderboard,
+		pings::pings, rep::rep, roll::roll, status::status, test::test,
 	},
+	config,
 	config::get_testing_guild,
 	data::Database,
-	systems::xp_leveling::XPHandler,
+	systems::{
+		level_rewards::LevelRewardGranter,
+		reputation::ThanksHandler,
+		xp_leveling::{LevelUpAnnouncer, XPHandler},
+	},
 };
-use eyre::Result;
-use poise::{builtins::create_application_commands, serenity_prelude as serenity};
 use serenity::Command;
 use tokio::sync::Mutex;
 
-pub mod commands;
-pub mod config;
-pub mod data;
-pub mod systems;
-pub mod utils;
-
-type Error = Box<dyn std::error::Error + Send + Sync>;
-type Context<'a> = poise::Context<'a, Arc<Mutex<Database>>, Error>;
-
 async fn eyre_main() -> Result<()> {
 	// Create db
-	let db = Arc::new(Mutex::new(Database::new(Path::new("./db.json").into())?));
+	let backend = config::get_storage_backend();
+
+	let db = Arc::new(Mutex::new(Database::open(backend, backend.default_path())?));
 
 	// We need message perms
 	let intents = serenity::GatewayIntents::all();
@@ -40,10 +43,24 @@ async fn eyre_main() -> Result<()> {
 			commands: vec![
 				counter(),
 				coinflip(),
+				daily(),
 				status(),
 				inventory(),
+				leaderboard(),
+				pings(),
+				rep(),
+				roll(),
 				admin_give(),
+				admin_history(),
+				admin_revert(),
 				admin_burn(),
+				admin_compact(),
+				admin_end_season(),
+				admin_global_profile(),
+				admin_anti_spam(),
+				admin_announcements(),
+				admin_leveling(),
+				admin_level_reward(),
 				test(),
 				goto(),
 			],
@@ -71,8 +88,17 @@ async fn eyre_main() -> Result<()> {
 	let mut client = serenity::ClientBuilder::new(config::get_token(), intents)
 		.framework(framework)
 		.event_handler(XPHandler::new(Arc::clone(&db)))
+		.event_handler(ThanksHandler::new(Arc::clone(&db)))
 		.await?;
 
+	// And tell people when they level up, and reward them for it
+	{
+		let mut db = db.lock().await;
+
+		db.subscribe(LevelUpAnnouncer::new(Arc::clone(&client.http)));
+		db.subscribe(LevelRewardGranter::new(Arc::clone(&client.http)));
+	}
+
 	// And run it all
 	client.start().await?;
 
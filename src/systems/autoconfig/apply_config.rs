@@ -1,13 +1,14 @@
 use crate::{
 	config::get_bot_id,
 	data::{
-		rng::Random,
+		envelope::{EventMeta, EventSource},
+		rng::Seed,
 		state::{DBEvent, DBServer},
-		Database,
 	},
 	systems::autoconfig::data::{
 		role, ServerConfig, ServerConfigChannel::Category, ServerConfigPermissions,
 	},
+	utils::GetDB,
 	Context,
 };
 use serenity::all::{
@@ -16,6 +17,7 @@ use serenity::all::{
 };
 use std::collections::HashMap;
 use thiserror::Error;
+use tokio::sync::Mutex;
 
 #[derive(Error, Debug)]
 pub enum AutoconfigError {
@@ -52,7 +54,7 @@ impl ConsistentOrder for Vec<PermissionOverwrite> {
 }
 
 pub fn overrides(
-	guild_id: &GuildId,
+	_guild_id: &GuildId,
 	server: &DBServer,
 	perms: &ServerConfigPermissions,
 ) -> Vec<PermissionOverwrite> {
@@ -63,6 +65,18 @@ pub fn overrides(
 		.collect::<Vec<PermissionOverwrite>>()
 }
 
+async fn bot_position(ctx: &Context<'_>, guild_id: &GuildId) -> eyre::Result<u16> {
+	let me = guild_id.member(ctx, get_bot_id()).await?;
+
+	let position = ctx
+		.cache()
+		.guild(guild_id)
+		.and_then(|guild| guild.member_highest_role(&me).map(|role| role.position))
+		.ok_or(AutoconfigError::OptionIsNone)?;
+
+	Ok(position)
+}
+
 trait LazyOrder {
 	async fn lazy_order(
 		&self,
@@ -113,280 +127,314 @@ impl LazyOrder for GuildId {
 	}
 }
 
-impl Database {
-	pub async fn update_config(
-		&mut self,
-		ctx: &Context<'_>,
-		guild_id: &GuildId,
-	) -> eyre::Result<()> {
-		self.update_server(ctx, self.get_config(guild_id), guild_id)
-			.await?;
+/// Only one guild is reconciled at a time, so two runs don't both create the
+/// same roles and channels
+static RECONCILING: Mutex<()> = Mutex::const_new(());
 
-		Ok(())
-	}
+/// Makes a guild match its [`ServerConfig`]
+///
+/// This reads what it needs from the database up front, and only locks it
+/// again for a moment to commit each change, so other commands keep running
+/// while we wait on Discord.
+pub async fn update_config(ctx: &Context<'_>, guild_id: &GuildId) -> eyre::Result<()> {
+	let _reconciling = RECONCILING.lock().await;
 
-	async fn update_server(
-		&mut self,
-		ctx: &Context<'_>,
-		server_config: ServerConfig,
-		guild_id: &GuildId,
-	) -> eyre::Result<()> {
-		let mut server = self.state().get_server_or_default(guild_id);
+	let server_config = ctx.db("autoconfig").await.get_config(guild_id);
+
+	update_server(ctx, server_config, guild_id).await?;
+
+	Ok(())
+}
+
+/// Adds an autoconfig event and returns the guild's state after it
+async fn commit(ctx: &Context<'_>, guild_id: &GuildId, event: DBEvent) -> eyre::Result<DBServer> {
+	let mut db = ctx.db("autoconfig").await;
 
-		if server.roles.get(&role("all")) != Some(&guild_id.everyone_role()) {
-			self.add(DBEvent::RoleAdd {
+	db.add(
+		event,
+		EventMeta::now(
+			EventSource::System("autoconfig".to_string()),
+			Some(ctx.author().id),
+			Some(*guild_id),
+		),
+	)?;
+
+	Ok(db.state().get_server_or_default(guild_id))
+}
+
+async fn update_server(
+	ctx: &Context<'_>,
+	server_config: ServerConfig,
+	guild_id: &GuildId,
+) -> eyre::Result<()> {
+	let mut server = ctx
+		.data()
+		.lock()
+		.await
+		.state()
+		.get_server_or_default(guild_id);
+
+	if server.roles.get(&role("all")) != Some(&guild_id.everyone_role()) {
+		server = commit(
+			ctx,
+			guild_id,
+			DBEvent::RoleAdd {
 				server: *guild_id,
 				id: role("all"),
 				discord_id: guild_id.everyone_role(),
-			})?;
-
-			server = self.state().get_server_or_default(guild_id);
-		}
+			},
+		)
+		.await?;
+	}
 
-		let mut roles = guild_id.roles(ctx).await?;
+	let mut roles = guild_id.roles(ctx).await?;
 
-		// Step A.1: Ensure all declared roles exist
-		for id in server_config.roles.keys() {
-			let exists = server.roles.contains_key(id) && roles.contains_key(&server.roles[id]);
+	// Step A.1: Ensure all declared roles exist
+	for id in server_config.roles.keys() {
+		let exists = server.roles.contains_key(id) && roles.contains_key(&server.roles[id]);
 
-			if !exists {
-				let role = guild_id
-					.create_role(ctx, EditRole::new().name("name pending"))
-					.await?;
+		if !exists {
+			let role = guild_id
+				.create_role(ctx, EditRole::new().name("name pending"))
+				.await?;
 
-				self.add(DBEvent::RoleAdd {
+			server = commit(
+				ctx,
+				guild_id,
+				DBEvent::RoleAdd {
 					server: *guild_id,
 					id: id.clone(),
 					discord_id: role.id,
-				})?;
-
-				server = self.state().get_server_or_default(guild_id);
+				},
+			)
+			.await?;
 
-				roles.insert(role.id, role);
-			}
+			roles.insert(role.id, role);
 		}
+	}
 
-		// Step A.2: Mark used roles
-		let mut used_roles = vec![];
+	// Step A.2: Mark used roles
+	let mut used_roles = vec![];
 
-		for id in server_config.roles.keys() {
-			let discord_id = server.roles[id];
+	for id in server_config.roles.keys() {
+		let discord_id = server.roles[id];
 
-			used_roles.push(discord_id);
-		}
+		used_roles.push(discord_id);
+	}
 
-		// Step A.3: Delete unused roles
-		let (_, my_pos) = guild_id
-			.member(ctx, get_bot_id())
-			.await?
-			.highest_role_info(ctx)
-			.ok_or(AutoconfigError::OptionIsNone)?;
+	// Step A.3: Delete unused roles
+	let my_pos = bot_position(ctx, guild_id).await?;
 
-		for (id, role) in &mut roles {
-			if used_roles.contains(id) {
-				// If the role is used, don't delete it
-				continue;
-			}
+	for (id, role) in &mut roles {
+		if used_roles.contains(id) {
+			// If the role is used, don't delete it
+			continue;
+		}
 
-			if role.position >= my_pos {
-				// If the role is above us, don't delete it
-				continue;
-			}
+		if role.position >= my_pos {
+			// If the role is above us, don't delete it
+			continue;
+		}
 
-			if role.tags.bot_id.is_some() {
-				// If it's a bot role, don't delete it
-				continue;
-			}
+		if role.tags.bot_id.is_some() {
+			// If it's a bot role, don't delete it
+			continue;
+		}
 
-			if role.id == role.guild_id.everyone_role() {
-				// If it's the @everyone role, don't delete it
-				continue;
-			}
+		if role.id == role.guild_id.everyone_role() {
+			// If it's the @everyone role, don't delete it
+			continue;
+		}
 
-			role.delete(ctx).await?;
+		role.delete(ctx).await?;
 
-			let config_id = server
-				.roles
-				.iter()
-				.find(|(_, other)| role.id.get() == other.get());
+		let config_id = server
+			.roles
+			.iter()
+			.find(|(_, other)| role.id.get() == other.get());
 
-			if config_id.is_none() {
-				continue;
-			}
+		if config_id.is_none() {
+			continue;
+		}
 
-			let (role_cfg_id, _) = config_id.unwrap();
+		let (role_cfg_id, _) = config_id.unwrap();
 
-			self.add(DBEvent::RoleForget {
+		commit(
+			ctx,
+			guild_id,
+			DBEvent::RoleForget {
 				id: role_cfg_id.clone(),
 				server: *guild_id,
-			})?;
-		}
+			},
+		)
+		.await?;
+	}
 
-		// Step A.4: Configure misconfigured roles
-		for (id, config) in &server_config.roles {
-			let mut role = roles[&server.roles[id]].clone();
-			let dirty = role.colour != config.color
-				|| role.name != config.name
-				|| role.permissions != config.permissions;
+	// Step A.4: Configure misconfigured roles
+	for (id, config) in &server_config.roles {
+		let mut role = roles[&server.roles[id]].clone();
+		let dirty = role.colour != config.color
+			|| role.name != config.name
+			|| role.permissions != config.permissions;
 
-			if dirty {
-				role.edit(
-					ctx,
-					EditRole::new()
-						.name(&config.name)
-						.colour(config.color)
-						.permissions(config.permissions),
-				)
-				.await?;
-			}
+		if dirty {
+			role.edit(
+				ctx,
+				EditRole::new()
+					.name(&config.name)
+					.colour(config.color)
+					.permissions(config.permissions),
+			)
+			.await?;
 		}
+	}
 
-		// Step A.5: Order roles
-		let (_, my_pos) = guild_id
-			.member(ctx, get_bot_id())
-			.await?
-			.highest_role_info(ctx)
-			.ok_or(AutoconfigError::OptionIsNone)?;
+	// Step A.5: Order roles
+	let my_pos = bot_position(ctx, guild_id).await?;
 
-		{
-			let mut idx = my_pos;
+	{
+		let mut idx = my_pos;
 
-			for role in server_config.role_order {
-				idx -= 1;
+		for role in server_config.role_order {
+			idx -= 1;
 
-				let discord = roles[&server.roles[&role]].clone();
+			let discord = roles[&server.roles[&role]].clone();
 
-				if idx > my_pos || discord.position > my_pos {
-					println!(
-						"warn: role {} will go over, or is over current role, cancelled operation",
-						role.0
-					);
-					continue;
-				}
+			if idx > my_pos || discord.position > my_pos {
+				println!(
+					"warn: role {} will go over, or is over current role, cancelled operation",
+					role.0
+				);
+				continue;
+			}
 
-				if discord.position != idx {
-					guild_id.edit_role_position(ctx, discord, idx).await?;
-				}
+			if discord.position != idx {
+				guild_id.edit_role_position(ctx, discord, idx).await?;
 			}
 		}
+	}
 
-		// Step B.1: Ensure all declared channels exist
-		let mut channels = guild_id.channels(ctx).await?;
-
-		for id in server_config.channels.keys() {
-			let exists = server.channels.contains_key(id)
-				&& {
-					if !channels.contains_key(&server.channels[id]) {
-						channels = guild_id.channels(ctx).await?
-					};
-					true
-				} && channels.contains_key(&server.channels[id])
-				&& channels[&server.channels[id]].kind == server_config.channels[id].kind(); // fixme: hell
-
-			if !exists {
-				let channel = guild_id
-					.create_channel(
-						ctx,
-						CreateChannel::new(format!(
-							"uninitialized-{}",
-							Random::new().get(0f32..1f32)
-						))
+	// Step B.1: Ensure all declared channels exist
+	let mut channels = guild_id.channels(ctx).await?;
+
+	for id in server_config.channels.keys() {
+		let exists = server.channels.contains_key(id)
+			&& {
+				if !channels.contains_key(&server.channels[id]) {
+					channels = guild_id.channels(ctx).await?
+				};
+				true
+			} && channels.contains_key(&server.channels[id])
+			&& channels[&server.channels[id]].kind == server_config.channels[id].kind(); // fixme: hell
+
+		if !exists {
+			let channel = guild_id
+				.create_channel(
+					ctx,
+					CreateChannel::new(format!("uninitialized-{}", Seed::new()))
 						.kind(server_config.channels[id].kind()),
-					)
-					.await?;
+				)
+				.await?;
 
-				self.add(DBEvent::ChannelAdd {
+			server = commit(
+				ctx,
+				guild_id,
+				DBEvent::ChannelAdd {
 					server: *guild_id,
 					id: id.clone(),
 					discord_id: channel.id,
-				})?;
-
-				server = self.state().get_server_or_default(guild_id); // fixme: this is f***ing evil
+				},
+			)
+			.await?; // fixme: this is f***ing evil
 
-				channels.insert(server.channels[id], channel);
-			}
+			channels.insert(server.channels[id], channel);
 		}
+	}
 
-		// B.2. Put settings
-		for (id, config) in &server_config.channels {
-			let channel_id = server.channels[id];
+	// B.2. Put settings
+	for (id, config) in &server_config.channels {
+		let channel_id = server.channels[id];
 
-			let guild = &channels[&channel_id];
+		let guild = &channels[&channel_id];
 
-			if config.check_dirty(guild, &server) {
-				println!("{} is dirty", channel_id.name(&ctx).await?);
-				channel_id
-					.edit(ctx, config.build(guild_id, &server))
-					.await?;
-			}
+		if config.check_dirty(guild, &server) {
+			println!("{} is dirty", channel_id.name(&ctx).await?);
+			channel_id
+				.edit(ctx, config.build(guild_id, &server))
+				.await?;
 		}
+	}
 
-		// B.3. Find used channels
-		let mut used_channels = vec![];
+	// B.3. Find used channels
+	let mut used_channels = vec![];
 
-		for id in server_config.channels.keys() {
-			let channel_id = server.channels[id];
+	for id in server_config.channels.keys() {
+		let channel_id = server.channels[id];
 
-			used_channels.push(channel_id);
-		}
+		used_channels.push(channel_id);
+	}
 
-		// B.4. Burn it down
-		for (id, channel) in guild_id.channels(ctx).await? {
-			if !used_channels.contains(&id) {
-				for (channel, sid) in &server.channels {
-					if *sid == id {
-						self.add(DBEvent::ChannelForget {
+	// B.4. Burn it down
+	for (id, channel) in guild_id.channels(ctx).await? {
+		if !used_channels.contains(&id) {
+			for (channel, sid) in &server.channels {
+				if *sid == id {
+					commit(
+						ctx,
+						guild_id,
+						DBEvent::ChannelForget {
 							id: channel.clone(),
 							server: *guild_id,
-						})?;
-					}
+						},
+					)
+					.await?;
 				}
-				channel.delete(ctx).await?;
 			}
+			channel.delete(ctx).await?;
 		}
+	}
 
-		// B.5. Arrange
-		guild_id
-			.lazy_order(
-				ctx,
-				&server_config
-					.children
-					.iter()
-					.map(|x| server.channels[x])
-					.collect::<Vec<ChannelId>>(),
-				&channels,
-			)
-			.await?;
+	// B.5. Arrange
+	guild_id
+		.lazy_order(
+			ctx,
+			&server_config
+				.children
+				.iter()
+				.map(|x| server.channels[x])
+				.collect::<Vec<ChannelId>>(),
+			&channels,
+		)
+		.await?;
 
-		for (id, config) in &server_config.channels {
-			if let Category { name: _, children } = config {
-				let channel_id = server.channels[id];
+	for (id, config) in &server_config.channels {
+		if let Category { name: _, children } = config {
+			let channel_id = server.channels[id];
 
-				for child in children.iter() {
-					let child_id = server.channels[child];
+			for child in children.iter() {
+				let child_id = server.channels[child];
 
-					let mut guild = channels[&child_id].clone();
+				let mut guild = channels[&child_id].clone();
 
-					if guild.parent_id != Some(channel_id) {
-						guild
-							.edit(ctx, EditChannel::new().category(channel_id))
-							.await?;
-					}
+				if guild.parent_id != Some(channel_id) {
+					guild
+						.edit(ctx, EditChannel::new().category(channel_id))
+						.await?;
 				}
-
-				guild_id
-					.lazy_order(
-						ctx,
-						&children
-							.iter()
-							.map(|x| server.channels[x])
-							.collect::<Vec<ChannelId>>(),
-						&channels,
-					)
-					.await?;
 			}
-		}
 
-		Ok(())
+			guild_id
+				.lazy_order(
+					ctx,
+					&children
+						.iter()
+						.map(|x| server.channels[x])
+						.collect::<Vec<ChannelId>>(),
+					&channels,
+				)
+				.await?;
+		}
 	}
+
+	Ok(())
 }
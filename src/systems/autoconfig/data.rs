@@ -13,6 +13,9 @@ pub fn channel(id: &str) -> ServerConfigChannelId { ServerConfigChannelId(id.to_
 
 pub fn role(id: &str) -> ServerConfigRoleId { ServerConfigRoleId(id.to_string()) }
 
+/// The role given to users that reach `level`
+pub fn level_reward_role(level: u64) -> ServerConfigRoleId { role(&format!("levels/{level}")) }
+
 pub struct ServerConfig {
 	pub children: Vec<ServerConfigChannelId>,
 	pub channels: HashMap<ServerConfigChannelId, ServerConfigChannel>,
@@ -77,7 +80,7 @@ impl ServerConfigTextLike {
 		false
 	}
 
-	fn build(&self, guild: &GuildId, server: &DBServer) -> EditChannel {
+	fn build(&self, guild: &GuildId, server: &DBServer) -> EditChannel<'_> {
 		EditChannel::new()
 			.name(self.name.clone())
 			.topic(self.description.clone())
@@ -110,7 +113,7 @@ impl ServerConfigChannel {
 		}
 	}
 
-	pub fn build(&self, guild_id: &GuildId, server: &DBServer) -> EditChannel {
+	pub fn build(&self, guild_id: &GuildId, server: &DBServer) -> EditChannel<'_> {
 		(match self {
 			ServerConfigChannel::Text(tl) => tl.build(guild_id, server),
 			ServerConfigChannel::Rules(tl) => tl.build(guild_id, server),
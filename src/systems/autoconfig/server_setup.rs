@@ -1,5 +1,5 @@
 use crate::{
-	data::{places::PLACES, Database},
+	data::{announcements::AnnouncementMode, places::PLACES, Database},
 	systems::autoconfig::{
 		data,
 		data::{
@@ -29,6 +29,9 @@ macro_rules! channel {
 	}};
 }
 
+/// Where level-ups go in [`AnnouncementMode::Channel`]
+pub const LEVEL_UPS_CHANNEL: &str = "chats/level-ups";
+
 impl Database {
 	pub fn get_config(&self, gid: &GuildId) -> ServerConfig {
 		let mut config = ServerConfig {
@@ -59,6 +62,21 @@ impl Database {
 			}
 		);
 
+		let server = self.state().get_server_or_default(gid);
+
+		// Higher levels go above lower ones
+		for (level, name) in server.level_rewards.iter().rev() {
+			role!(
+				config,
+				&data::level_reward_role(*level).0,
+				ServerConfigRole {
+					name: format!("Level {level} — {name}"),
+					color: Colour(0xF1C40F),
+					permissions: Permissions::empty()
+				}
+			);
+		}
+
 		for place in PLACES {
 			role!(
 				config,
@@ -72,25 +90,48 @@ impl Database {
 		}
 
 		// Channels
+		let mut chats = (1..5)
+			.map(|x| {
+				channel!(
+					config,
+					&format!("chats/global-{}", x),
+					ServerConfigChannel::Text(ServerConfigTextLike {
+						name: format!("global-chat-{}", x),
+						description: "A global chat".to_string(),
+						permissions: ServerConfigPermissions {
+							base: Permissions::default(),
+							overrides: vec![]
+						}
+					})
+				)
+			})
+			.collect::<Vec<_>>();
+
+		if server.announcements.mode == AnnouncementMode::Channel {
+			chats.push(channel!(
+				config,
+				LEVEL_UPS_CHANNEL,
+				ServerConfigChannel::Text(ServerConfigTextLike {
+					name: "level-ups".to_string(),
+					description: "Who levelled up".to_string(),
+					permissions: ServerConfigPermissions {
+						base: Permissions::default(),
+						overrides: vec![ServerConfigPermissionOverwrite {
+							role: data::role("all"),
+							allow: Permissions::empty(),
+							deny: Permissions::SEND_MESSAGES,
+						}]
+					}
+				})
+			));
+		}
+
 		config.children.push(channel!(
 			config,
 			"chats",
 			ServerConfigChannel::Category {
 				name: "~ EVERYWHERE ~".to_string(),
-				children: (1..5)
-					.map(|x| channel!(
-						config,
-						&format!("chats/global-{}", x),
-						ServerConfigChannel::Text(ServerConfigTextLike {
-							name: format!("global-chat-{}", x),
-							description: "A global chat".to_string(),
-							permissions: ServerConfigPermissions {
-								base: Permissions::default(),
-								overrides: vec![]
-							}
-						})
-					))
-					.collect(),
+				children: chats,
 			}
 		));
 
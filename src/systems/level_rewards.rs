@@ -0,0 +1,154 @@
+use std::{cmp::Ordering, collections::BTreeSet, sync::Arc};
+
+use serenity::all::{GuildId, Http, RoleId, UserId};
+
+use crate::data::{
+	bus::{Committed, Subscriber},
+	state::DBState,
+	user::DBUser,
+};
+
+/// Keeps users' reward roles in line with their levels, giving roles for levels
+/// they reach and taking them away when their level drops
+#[derive(Debug)]
+pub struct LevelRewardGranter {
+	http: Arc<Http>,
+}
+
+impl LevelRewardGranter {
+	pub fn new(http: Arc<Http>) -> Self { Self { http } }
+}
+
+impl Subscriber for LevelRewardGranter {
+	fn committed(&self, event: &Committed) {
+		let changes = event
+			.after
+			.servers
+			.iter()
+			.filter(|(_, server)| !server.level_rewards.is_empty())
+			.flat_map(|(guild, _)| {
+				reward_role_changes(event.before, event.after, *guild, event.envelope.subject())
+			})
+			.collect::<Vec<_>>();
+
+		if changes.is_empty() {
+			return;
+		}
+
+		let http = Arc::clone(&self.http);
+
+		tokio::spawn(async move {
+			for change in changes {
+				for role in change.add {
+					let _ = http
+						.add_member_role(change.guild, change.user, role, Some("Level reward"))
+						.await;
+				}
+
+				for role in change.remove {
+					let _ = http
+						.remove_member_role(change.guild, change.user, role, Some("Level reward"))
+						.await;
+				}
+			}
+		});
+	}
+}
+
+/// The reward roles a user should get and lose in a guild
+#[derive(Debug, PartialEq, Eq)]
+pub struct RoleChange {
+	pub guild: GuildId,
+	pub user: UserId,
+	pub add: Vec<RoleId>,
+	pub remove: Vec<RoleId>,
+}
+
+/// How the reward roles in `guild` change going from `before` to `after`, for
+/// `user`, or everyone when it's `None`
+///
+/// Users hold the roles for every level they've reached, so only levels in
+/// between the old and new one change.
+pub fn reward_role_changes(
+	before: &DBState,
+	after: &DBState,
+	guild: GuildId,
+	user: Option<UserId>,
+) -> Vec<RoleChange> {
+	let server = after.get_server_or_default(&guild);
+
+	let level = |state: &DBState, user: &UserId| {
+		state
+			.progress(Some(guild))
+			.users
+			.get(user)
+			.map_or(DBUser::default().level, |x| x.level)
+	};
+
+	let users = match user {
+		Some(user) => BTreeSet::from([user]),
+		None => before
+			.progress(Some(guild))
+			.users
+			.keys()
+			.chain(after.progress(Some(guild)).users.keys())
+			.copied()
+			.collect(),
+	};
+
+	users
+		.into_iter()
+		.filter_map(|user| {
+			let (from, to) = (level(before, &user), level(after, &user));
+
+			let (add, remove) = match from.cmp(&to) {
+				Ordering::Less => (server.level_reward_roles(from + 1..=to), vec![]),
+				Ordering::Greater => (vec![], server.level_reward_roles(to + 1..=from)),
+				Ordering::Equal => return None,
+			};
+
+			if add.is_empty() && remove.is_empty() {
+				return None;
+			}
+
+			Some(RoleChange {
+				guild,
+				user,
+				add,
+				remove,
+			})
+		})
+		.collect()
+}
+
+/// Gives the reward role for `level` to everyone in `guild` that's already
+/// past it, returns how many got it
+///
+/// Users that left the guild are skipped.
+pub async fn backfill(http: &Http, state: &DBState, guild: GuildId, level: u64) -> usize {
+	let Some(role) = state
+		.get_server_or_default(&guild)
+		.level_reward_roles(level..=level)
+		.pop()
+	else {
+		return 0;
+	};
+
+	let mut granted = 0;
+
+	for (user, db_user) in &state.progress(Some(guild)).users {
+		if db_user.level < level {
+			continue;
+		}
+
+		if http
+			.add_member_role(guild, *user, role, Some("Level reward"))
+			.await
+			.is_ok()
+		{
+			granted += 1;
+		}
+	}
+
+	granted
+}
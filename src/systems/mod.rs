@@ -1,2 +1,5 @@
 pub mod autoconfig;
+pub mod level_rewards;
+pub mod reputation;
+pub mod voice_xp;
 pub mod xp_leveling;
@@ -0,0 +1,68 @@
+use crate::data::{
+	envelope::{EventMeta, EventSource},
+	reputation::{RepSource, THANKS_REACTIONS},
+	state::DBEvent,
+	Database,
+};
+use serenity::{
+	all::{Context, EventHandler, Reaction},
+	async_trait,
+};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Gives reputation to the authors of messages people react to with one of
+/// [`THANKS_REACTIONS`]
+pub struct ThanksHandler {
+	db: Arc<Mutex<Database>>,
+}
+
+impl ThanksHandler {
+	pub fn new(db: Arc<Mutex<Database>>) -> Self { Self { db } }
+}
+
+#[async_trait]
+impl EventHandler for ThanksHandler {
+	async fn reaction_add(&self, ctx: Context, reaction: Reaction) {
+		if !THANKS_REACTIONS
+			.iter()
+			.any(|x| reaction.emoji.unicode_eq(x))
+		{
+			return;
+		}
+
+		let Some(giver) = reaction.user_id else {
+			return;
+		};
+
+		if reaction.member.as_ref().is_some_and(|x| x.user.bot) {
+			return;
+		}
+
+		let Ok(message) = reaction.message(&ctx).await else {
+			return;
+		};
+
+		if message.author.bot {
+			return;
+		}
+
+		let meta = EventMeta::now(
+			EventSource::System("reputation".to_string()),
+			Some(giver),
+			reaction.guild_id,
+		)
+		.in_channel(reaction.channel_id);
+
+		// Thanks past the cooldown or budget just don't count, no need to
+		// tell anyone
+		let _ = self.db.lock().await.add(
+			DBEvent::GiveReputation {
+				giver,
+				receiver: message.author.id,
+				source: RepSource::Thanks,
+			},
+			meta,
+		);
+	}
+}
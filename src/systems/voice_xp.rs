@@ -0,0 +1,181 @@
+use std::collections::{HashMap, HashSet};
+
+use serenity::all::{ChannelId, GuildId, Timestamp, UserId};
+
+/// Someone in a voice channel, as far as XP cares
+#[derive(Clone, Copy, Debug)]
+pub struct VoiceMember {
+	pub user: UserId,
+	pub channel: ChannelId,
+
+	/// Deafened by themselves or by a moderator
+	pub deaf: bool,
+
+	/// Muted by themselves or by a moderator, they still count as company
+	pub muted: bool,
+	pub bot: bool,
+}
+
+/// Active minutes someone spent in a voice channel since they were last
+/// handed out, to be recorded as a
+/// [`crate::data::state::DBEvent::VoiceSession`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccruedMinutes {
+	pub guild: GuildId,
+	pub user: UserId,
+	pub channel: ChannelId,
+
+	/// When the stay in the channel started, not when these minutes did
+	pub started: Timestamp,
+	pub active_minutes: u64,
+}
+
+#[derive(Clone, Debug)]
+struct Session {
+	channel: ChannelId,
+	started: i64,
+
+	/// When the user last started counting as active, if they are
+	active_since: Option<i64>,
+	active_seconds: i64,
+}
+
+impl Session {
+	fn set_active(&mut self, active: bool, now: i64) {
+		match (self.active_since, active) {
+			(None, true) => self.active_since = Some(now),
+			(Some(since), false) => {
+				self.active_seconds += now - since;
+				self.active_since = None;
+			}
+			_ => {}
+		}
+	}
+
+	/// Hands out the whole minutes of activity up to `now`, the seconds left
+	/// over count towards the next ones
+	fn take_minutes(&mut self, now: i64) -> u64 {
+		if let Some(since) = self.active_since {
+			self.active_seconds += now - since;
+			self.active_since = Some(now);
+		}
+
+		let minutes = self.active_seconds / 60;
+		self.active_seconds -= minutes * 60;
+
+		minutes as u64
+	}
+
+	fn accrued(&mut self, guild: GuildId, user: UserId, now: i64) -> Option<AccruedMinutes> {
+		let active_minutes = self.take_minutes(now);
+
+		(active_minutes > 0).then(|| AccruedMinutes {
+			guild,
+			user,
+			channel: self.channel,
+			started: Timestamp::from_unix_timestamp(self.started).unwrap_or_default(),
+			active_minutes,
+		})
+	}
+}
+
+/// Keeps time for everyone in voice
+///
+/// A user is active while they aren't muted or deafened, aren't in the AFK
+/// channel and have someone to talk to, meaning another person in the channel
+/// that isn't a bot or deafened. Every time voice changes in a guild, call
+/// [`VoiceTracker::update`] with everyone that's in voice there now, and call
+/// [`VoiceTracker::flush`] every so often so long stays don't have to end
+/// before they count.
+#[derive(Debug, Default)]
+pub struct VoiceTracker {
+	sessions: HashMap<(GuildId, UserId), Session>,
+}
+
+impl VoiceTracker {
+	/// Catches up with who's in voice in `guild`, returns the minutes accrued
+	/// there since the last update or flush
+	pub fn update(
+		&mut self,
+		guild: GuildId,
+		members: &[VoiceMember],
+		afk: Option<ChannelId>,
+		now: Timestamp,
+	) -> Vec<AccruedMinutes> {
+		let now = now.unix_timestamp();
+
+		let present = members
+			.iter()
+			.filter(|x| !x.bot)
+			.map(|x| (x.user, x))
+			.collect::<HashMap<_, _>>();
+
+		// Close sessions of people that left or moved
+		let ended = self
+			.sessions
+			.iter()
+			.filter(|((g, user), session)| {
+				*g == guild
+					&& present
+						.get(user)
+						.is_none_or(|x| x.channel != session.channel)
+			})
+			.map(|(key, _)| *key)
+			.collect::<Vec<_>>();
+
+		let mut accrued = vec![];
+
+		for key in ended {
+			let mut session = self.sessions.remove(&key).unwrap();
+
+			session.set_active(false, now);
+
+			accrued.extend(session.accrued(guild, key.1, now));
+		}
+
+		// And work out who's active now
+		let listening = present
+			.values()
+			.filter(|x| !x.deaf)
+			.map(|x| (x.channel, x.user))
+			.collect::<HashSet<_>>();
+
+		for member in present.values() {
+			let company = listening
+				.iter()
+				.any(|(channel, user)| *channel == member.channel && *user != member.user);
+
+			let active = company && !member.deaf && !member.muted && Some(member.channel) != afk;
+
+			self.sessions
+				.entry((guild, member.user))
+				.or_insert_with(|| Session {
+					channel: member.channel,
+					started: now,
+					active_since: None,
+					active_seconds: 0,
+				})
+				.set_active(active, now);
+		}
+
+		accrued.extend(
+			self.sessions
+				.iter_mut()
+				.filter(|((g, _), _)| *g == guild)
+				.filter_map(|((g, user), session)| session.accrued(*g, *user, now)),
+		);
+
+		accrued
+	}
+
+	/// The minutes accrued everywhere since the last update or flush, without
+	/// ending anyone's session
+	pub fn flush(&mut self, now: Timestamp) -> Vec<AccruedMinutes> {
+		let now = now.unix_timestamp();
+
+		self.sessions
+			.iter_mut()
+			.filter_map(|((guild, user), session)| session.accrued(*guild, *user, now))
+			.collect()
+	}
+}
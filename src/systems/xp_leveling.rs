@@ -1,57 +1,275 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use serenity::{
-	all::{Context, EventHandler, Message},
+	all::{
+		ChannelId, Context, CreateAllowedMentions, CreateAttachment, CreateMessage, EventHandler,
+		Guild, GuildId, Http, Message, MessageId, Timestamp, UserId, VoiceState,
+	},
 	async_trait,
 };
 use tokio::sync::Mutex;
 
 use crate::{
-	data::{state::DBEvent::UserSendMessage, Database},
-	utils::AntiSpamCount,
+	data::{
+		announcements::AnnouncementMode,
+		bus::{Committed, Subscriber},
+		drawing::png_attachment,
+		envelope::{EventMeta, EventSource},
+		state::DBEvent::{UserSendMessage, VoiceSession},
+		user::DBUser,
+		Database,
+	},
+	systems::{
+		autoconfig::{data::channel, server_setup::LEVEL_UPS_CHANNEL},
+		voice_xp::{AccruedMinutes, VoiceMember, VoiceTracker},
+	},
+	utils::{AntiSpamCount, Fingerprint},
 };
 
+/// How often minutes in voice are recorded for people that are still there
+const VOICE_FLUSH_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
 pub struct XPHandler {
 	db: Arc<Mutex<Database>>,
+	voice: Arc<Mutex<VoiceTracker>>,
 }
 
 impl XPHandler {
-	pub fn new(db: Arc<Mutex<Database>>) -> Self { Self { db } }
+	/// Also starts recording voice minutes every [`VOICE_FLUSH_INTERVAL`], so
+	/// a restart only loses the last few
+	pub fn new(db: Arc<Mutex<Database>>) -> Self {
+		let voice = Arc::new(Mutex::new(VoiceTracker::default()));
+
+		tokio::spawn({
+			let db = Arc::clone(&db);
+			let voice = Arc::clone(&voice);
+
+			async move {
+				let mut interval = tokio::time::interval(VOICE_FLUSH_INTERVAL);
+
+				loop {
+					interval.tick().await;
+
+					let accrued = voice.lock().await.flush(Timestamp::now());
+
+					record_voice(&mut *db.lock().await, accrued);
+				}
+			}
+		});
+
+		Self { db, voice }
+	}
+}
+
+fn record_voice(db: &mut Database, accrued: Vec<AccruedMinutes>) {
+	for minutes in accrued {
+		let meta = EventMeta::now(
+			EventSource::System("voice_xp".to_string()),
+			Some(minutes.user),
+			Some(minutes.guild),
+		)
+		.in_channel(minutes.channel);
+
+		let _ = db.add(
+			VoiceSession {
+				user: minutes.user,
+				started: minutes.started,
+				active_minutes: minutes.active_minutes,
+			},
+			meta,
+		);
+	}
 }
 
 #[async_trait]
 impl EventHandler for XPHandler {
-	async fn message(&self, ctx: Context, msg: Message) {
-		let mut db = self.db.lock().await;
-
-		let level_before = db.state().get_user_or_default(&msg.author.id).level;
-
-		let _ = db.add(UserSendMessage {
-			user: msg.author.id,
-			length: msg.content.anti_spam_count(), /* Secret Shenanigans
-			                                        * note: we do
-			                                        * not store the
-			                                        * full message
-			                                        * 4
-			                                        * privacy */
-		});
+	async fn message(&self, _ctx: Context, msg: Message) {
+		let meta = EventMeta::now(
+			EventSource::System("xp_leveling".to_string()),
+			Some(msg.author.id),
+			msg.guild_id,
+		)
+		.from_message(msg.channel_id, msg.id);
 
-		let level_after = db.state().get_user_or_default(&msg.author.id).level;
-
-		if level_before != level_after {
-			let _ = msg
-				.reply_ping(
-					ctx.http,
-					format!(
-						"⬆️ Level up from {} to **{}**. {} xp until next level",
-						level_before,
-						level_after,
-						db.state()
-							.get_user_or_default(&msg.author.id)
-							.xp_until_next_level
-					),
-				)
-				.await;
+		let _ = self.db.lock().await.add(
+			UserSendMessage {
+				user: msg.author.id,
+				fingerprint: Some(msg.content.fingerprint()),
+				legacy_rules: false,
+				length: msg.content.anti_spam_count(), /* Secret Shenanigans
+				                                        * note: we do
+				                                        * not store the
+				                                        * full message
+				                                        * 4
+				                                        * privacy */
+			},
+			meta,
+		);
+	}
+
+	// Also sent for every guild on startup, so people already in voice count
+	async fn guild_create(&self, _ctx: Context, guild: Guild, _is_new: Option<bool>) {
+		let (members, afk) = voice_members(&guild);
+
+		self.update_voice(guild.id, &members, afk).await;
+	}
+
+	async fn voice_state_update(&self, ctx: Context, _old: Option<VoiceState>, new: VoiceState) {
+		let Some(guild_id) = new.guild_id else {
+			return;
+		};
+
+		let Some((members, afk)) = ctx.cache.guild(guild_id).map(|x| voice_members(&x)) else {
+			return;
+		};
+
+		self.update_voice(guild_id, &members, afk).await;
+	}
+}
+
+impl XPHandler {
+	async fn update_voice(&self, guild: GuildId, members: &[VoiceMember], afk: Option<ChannelId>) {
+		let accrued = self
+			.voice
+			.lock()
+			.await
+			.update(guild, members, afk, Timestamp::now());
+
+		record_voice(&mut *self.db.lock().await, accrued);
+	}
+}
+
+/// Everyone in voice in `guild`, and its AFK channel
+fn voice_members(guild: &Guild) -> (Vec<VoiceMember>, Option<ChannelId>) {
+	let members = guild
+		.voice_states
+		.values()
+		.filter_map(|x| {
+			Some(VoiceMember {
+				user: x.user_id,
+				channel: x.channel_id?,
+				deaf: x.deaf || x.self_deaf,
+				muted: x.mute || x.self_mute,
+				bot: guild.members.get(&x.user_id).is_some_and(|x| x.user.bot),
+			})
+		})
+		.collect::<Vec<_>>();
+
+	(
+		members,
+		guild.afk_metadata.as_ref().map(|x| x.afk_channel_id),
+	)
+}
+
+/// Congratulates users when they level up, where their guild's
+/// [`crate::data::announcements::AnnouncementConfig`] says to
+#[derive(Debug)]
+pub struct LevelUpAnnouncer {
+	http: Arc<Http>,
+}
+
+impl LevelUpAnnouncer {
+	pub fn new(http: Arc<Http>) -> Self { Self { http } }
+}
+
+enum Destination {
+	Reply(ChannelId, MessageId),
+	Channel(ChannelId),
+	Dm,
+}
+
+impl Subscriber for LevelUpAnnouncer {
+	fn committed(&self, event: &Committed) {
+		let meta = &event.envelope.meta;
+
+		let Some(user) = event.envelope.subject() else {
+			return;
+		};
+
+		let level_before = event.before.get_user_or_default(meta.guild, &user).level;
+		let user_after = event.after.get_user_or_default(meta.guild, &user);
+
+		if level_before == user_after.level {
+			return;
 		}
+
+		let server = meta.guild.map(|x| event.after.get_server_or_default(&x));
+
+		let config = server
+			.as_ref()
+			.map(|x| x.announcements.clone())
+			.unwrap_or_default();
+
+		let destination = match config.mode {
+			AnnouncementMode::Silent => None,
+			AnnouncementMode::Reply => match (meta.channel, meta.message) {
+				(Some(channel), Some(message)) => Some(Destination::Reply(channel, message)),
+				// Like voice, which has no message to reply to
+				(channel, _) => channel.map(Destination::Channel),
+			},
+			// Until autoconfig makes the channel, it's the same as replying
+			AnnouncementMode::Channel => server
+				.and_then(|x| x.channels.get(&channel(LEVEL_UPS_CHANNEL)).copied())
+				.or(meta.channel)
+				.map(Destination::Channel),
+			AnnouncementMode::Dm => Some(Destination::Dm),
+		};
+
+		let Some(destination) = destination else {
+			return;
+		};
+
+		let pinged = if event.after.unpinged_users.contains(&user) {
+			vec![]
+		} else {
+			vec![user]
+		};
+
+		let mut message = CreateMessage::new()
+			.content(config.render(
+				user,
+				level_before,
+				user_after.level,
+				user_after.xp_until_next_level,
+			))
+			.allowed_mentions(
+				CreateAllowedMentions::new()
+					.replied_user(!pinged.is_empty())
+					.users(pinged),
+			);
+
+		let season = event.after.progress(meta.guild).season();
+
+		let http = Arc::clone(&self.http);
+
+		tokio::spawn(async move {
+			if config.card {
+				if let Some(card) = card(&http, &user_after, user, season).await {
+					message = message.add_file(card);
+				}
+			}
+
+			let _ = match destination {
+				Destination::Reply(channel, replied) => {
+					channel
+						.send_message(&http, message.reference_message((channel, replied)))
+						.await
+				}
+				Destination::Channel(channel) => channel.send_message(&http, message).await,
+				Destination::Dm => user.direct_message(&http, message).await,
+			};
+		});
 	}
 }
+
+/// The user's profile card, showing their new level
+async fn card(
+	http: &Http,
+	db_user: &DBUser,
+	user: UserId,
+	season: usize,
+) -> Option<CreateAttachment> {
+	let user = user.to_user(http).await.ok()?;
+
+	png_attachment(&db_user.image(&user, season).await.ok()?, "level-up.png").ok()
+}
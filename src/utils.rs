@@ -3,26 +3,45 @@ use std::sync::Arc;
 
 use tokio::sync::{Mutex, MutexGuard};
 
-use crate::{data::Database, Error};
+use crate::{
+	data::{
+		envelope::{EventMeta, EventSource},
+		leveling::MessageXp,
+		Database,
+	},
+	Error,
+};
 
 pub trait GetDB {
-	fn db(&self, purpose: &str) -> impl std::future::Future<Output = MutexGuard<Database>> + Send;
+	fn db(
+		&self,
+		purpose: &str,
+	) -> impl std::future::Future<Output = MutexGuard<'_, Database>> + Send;
 }
 
 impl<'a> GetDB for poise::Context<'a, Arc<Mutex<Database>>, Error> {
-	async fn db(&self, _: &str) -> MutexGuard<Database> { self.data().lock().await }
+	async fn db(&self, _: &str) -> MutexGuard<'_, Database> { self.data().lock().await }
 }
 
-pub fn calculate_length_to_xp(len: &usize) -> u64 {
-	let curve = ((*len as f64) / 15f64).powf(2f64) * 5f64; // curve = (len / 15) ^ 2 * 5
-
-	let max = 15f64;
-
-	let limited = if curve > max { max } else { curve }; // Clamp the curve to 15
+pub trait Meta {
+	/// Metadata for an event caused by running this command
+	fn meta(&self) -> EventMeta;
+}
 
-	limited.round() as u64
+impl<'a> Meta for poise::Context<'a, Arc<Mutex<Database>>, Error> {
+	fn meta(&self) -> EventMeta {
+		EventMeta::now(
+			EventSource::Command(self.command().name.clone()),
+			Some(self.author().id),
+			self.guild_id(),
+		)
+		.in_channel(self.channel_id())
+	}
 }
 
+/// XP for a message on the default [`MessageXp`] curve
+pub fn calculate_length_to_xp(len: &usize) -> u64 { MessageXp::default().xp(*len) }
+
 pub trait AntiSpamCount {
 	fn anti_spam_count(&self) -> usize;
 }
@@ -41,6 +60,41 @@ impl AntiSpamCount for String {
 	}
 }
 
+pub trait Fingerprint {
+	/// A SimHash of the text's letter trigrams, texts that are almost the same
+	/// get fingerprints that differ in only a few bits
+	fn fingerprint(&self) -> u64;
+}
+
+impl Fingerprint for String {
+	fn fingerprint(&self) -> u64 {
+		let chars = self
+			.to_lowercase()
+			.chars()
+			.filter(|x| x.is_alphanumeric())
+			.collect::<Vec<_>>();
+
+		let mut weights = [0i64; 64];
+
+		for gram in chars.windows(chars.len().clamp(1, 3)) {
+			// FNV-1a, which unlike the std hasher won't change between releases
+			let hash = gram.iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, x| {
+				(hash ^ *x as u64).wrapping_mul(0x0100_0000_01b3)
+			});
+
+			for (bit, weight) in weights.iter_mut().enumerate() {
+				*weight += if hash >> bit & 1 == 1 { 1 } else { -1 };
+			}
+		}
+
+		weights
+			.iter()
+			.enumerate()
+			.filter(|(_, x)| **x > 0)
+			.fold(0, |acc, (bit, _)| acc | 1 << bit)
+	}
+}
+
 pub trait Admin {
 	fn is_admin(&self) -> bool;
 }
@@ -0,0 +1,93 @@
+mod common;
+
+use quicksilver::{
+	data::{
+		announcements::{AnnouncementConfig, AnnouncementMode},
+		envelope::{EventMeta, EventSource},
+		state::DBEvent,
+		Database,
+	},
+	systems::autoconfig::{data::channel, server_setup::LEVEL_UPS_CHANNEL},
+};
+use serenity::all::{GuildId, UserId};
+
+const GUILD: GuildId = GuildId::new(1);
+const USER: UserId = UserId::new(2);
+
+fn add(db: &mut Database, event: DBEvent) {
+	db.add(
+		event,
+		EventMeta::now(EventSource::Unknown, Some(USER), Some(GUILD)),
+	)
+	.unwrap();
+}
+
+#[test]
+fn templates_are_filled_in() {
+	let config = AnnouncementConfig {
+		template: "GG {user}, {previous} -> {level}, {xp} to go. {unknown}".to_string(),
+		..Default::default()
+	};
+
+	assert_eq!(
+		config.render(USER, 4, 5, 112),
+		"GG <@2>, 4 -> 5, 112 to go. {unknown}"
+	);
+}
+
+#[test]
+fn the_level_ups_channel_only_exists_in_channel_mode() {
+	let dir = tempfile::tempdir().unwrap();
+	let mut db = common::database(dir.path());
+
+	assert!(!db
+		.get_config(&GUILD)
+		.channels
+		.contains_key(&channel(LEVEL_UPS_CHANNEL)));
+
+	add(
+		&mut db,
+		DBEvent::SetAnnouncements {
+			server: GUILD,
+			config: AnnouncementConfig {
+				mode: AnnouncementMode::Channel,
+				..Default::default()
+			},
+		},
+	);
+
+	assert_eq!(
+		db.state().get_server_or_default(&GUILD).announcements.mode,
+		AnnouncementMode::Channel
+	);
+	assert!(db
+		.get_config(&GUILD)
+		.channels
+		.contains_key(&channel(LEVEL_UPS_CHANNEL)));
+}
+
+#[test]
+fn pings_can_be_turned_off_and_on() {
+	let dir = tempfile::tempdir().unwrap();
+	let mut db = common::database(dir.path());
+
+	add(
+		&mut db,
+		DBEvent::SetLevelUpPings {
+			user: USER,
+			enabled: false,
+		},
+	);
+
+	assert!(db.state().unpinged_users.contains(&USER));
+
+	add(
+		&mut db,
+		DBEvent::SetLevelUpPings {
+			user: USER,
+			enabled: true,
+		},
+	);
+
+	assert!(db.state().unpinged_users.is_empty());
+}
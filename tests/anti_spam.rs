@@ -0,0 +1,145 @@
+use quicksilver::{
+	data::{
+		anti_spam::{AntiSpamConfig, XpActivity},
+		envelope::{EventEnvelope, EventMeta, EventSource},
+		state::{DBEvent, DBState},
+	},
+	utils::Fingerprint,
+};
+use serenity::all::{ChannelId, GuildId, Timestamp, UserId};
+
+const GUILD: GuildId = GuildId::new(1);
+
+fn at(seconds: i64, channel: u64) -> EventMeta {
+	EventMeta::now(EventSource::Unknown, None, Some(GUILD))
+		.in_channel(ChannelId::new(channel))
+		.at(Timestamp::from_unix_timestamp(seconds).unwrap())
+}
+
+fn message(user: u64, seconds: i64, legacy_rules: bool) -> EventEnvelope {
+	EventEnvelope {
+		meta: at(seconds, 1),
+		event: DBEvent::UserSendMessage {
+			user: UserId::new(user),
+			length: 100,
+			fingerprint: Some(format!("message number {seconds} from {user}").fingerprint()),
+			legacy_rules,
+		},
+	}
+}
+
+fn replay(events: &[EventEnvelope]) -> DBState {
+	events.iter().fold(DBState::default(), |state, event| {
+		event.reduce_state(&state).unwrap()
+	})
+}
+
+fn level_progress(state: &DBState, user: u64) -> (u64, u64) {
+	let user = state.get_user_or_default(Some(GUILD), &UserId::new(user));
+	(user.level, user.this_levels_xp)
+}
+
+#[test]
+fn cooldown_limits_gains() {
+	let config = AntiSpamConfig::default();
+	let mut activity = XpActivity::default();
+
+	assert_eq!(activity.grant(&config, &at(0, 1), Some(1), 15), 15);
+	assert_eq!(activity.grant(&config, &at(30, 1), Some(u64::MAX), 15), 0);
+
+	// Messages during the cooldown don't extend it
+	assert_eq!(activity.grant(&config, &at(60, 1), Some(0), 15), 15);
+}
+
+#[test]
+fn per_channel_cooldowns_are_separate() {
+	let config = AntiSpamConfig {
+		per_channel: true,
+		..Default::default()
+	};
+	let mut activity = XpActivity::default();
+
+	assert_eq!(activity.grant(&config, &at(0, 1), None, 15), 15);
+	assert_eq!(activity.grant(&config, &at(1, 2), None, 15), 15);
+	assert_eq!(activity.grant(&config, &at(2, 1), None, 15), 0);
+}
+
+#[test]
+fn hourly_cap_is_a_rolling_window() {
+	let config = AntiSpamConfig {
+		cooldown: 0,
+		hourly_cap: Some(40),
+		..Default::default()
+	};
+	let mut activity = XpActivity::default();
+
+	assert_eq!(activity.grant(&config, &at(0, 1), None, 15), 15);
+	assert_eq!(activity.grant(&config, &at(600, 1), None, 15), 15);
+	assert_eq!(activity.grant(&config, &at(1200, 1), None, 15), 10);
+	assert_eq!(activity.grant(&config, &at(1800, 1), None, 15), 0);
+
+	// The first gain is now more than an hour old
+	assert_eq!(activity.grant(&config, &at(3600, 1), None, 15), 15);
+}
+
+#[test]
+fn near_duplicates_give_nothing() {
+	let config = AntiSpamConfig {
+		cooldown: 0,
+		..Default::default()
+	};
+	let mut activity = XpActivity::default();
+
+	let text = "hey everyone, does anyone want to play some games tonight?".to_string();
+	let similar = "hey everyone does anyone want to play some games tonight??".to_string();
+	let different = "I finally finished the book you recommended last week".to_string();
+
+	assert_eq!(
+		activity.grant(&config, &at(0, 1), Some(text.fingerprint()), 15),
+		15
+	);
+	assert_eq!(
+		activity.grant(&config, &at(1, 1), Some(similar.fingerprint()), 15),
+		0
+	);
+	assert_eq!(
+		activity.grant(&config, &at(2, 1), Some(different.fingerprint()), 15),
+		15
+	);
+}
+
+#[test]
+fn alternating_users_still_have_cooldowns() {
+	let alternating = (0..10)
+		.map(|x| message(1 + x as u64 % 2, x, false))
+		.collect::<Vec<_>>();
+
+	let state = replay(&alternating);
+
+	// Only the first message of each user was outside the cooldown
+	assert_eq!(level_progress(&state, 1), level_progress(&state, 2));
+	assert_eq!(
+		state.progress(Some(GUILD)).xp_activity[&UserId::new(1)]
+			.recent
+			.len(),
+		1
+	);
+}
+
+#[test]
+fn legacy_messages_replay_unchanged() {
+	let events = [
+		message(1, 0, true),
+		message(1, 1, true),
+		message(2, 2, true),
+		message(1, 3, true),
+	];
+
+	let state = replay(&events);
+
+	// User 1 got XP twice since only repeated messages were ignored back then
+	let once = replay(&events[..1]);
+
+	assert!(level_progress(&state, 1) > level_progress(&once, 1));
+	assert!(state.progress(Some(GUILD)).xp_activity.is_empty());
+}
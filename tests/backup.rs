@@ -0,0 +1,134 @@
+use quicksilver::{
+	config::get_backup_count,
+	data::{
+		envelope::{EventMeta, EventSource},
+		schema,
+		state::DBEvent,
+		storage::{backup, StorageBackend},
+		Database,
+	},
+};
+use serenity::all::{GuildId, UserId};
+use std::path::Path;
+
+fn count(db: &mut Database) {
+	db.add(
+		DBEvent::Counter {
+			user: UserId::new(5),
+		},
+		EventMeta::now(
+			EventSource::Command("counter".to_string()),
+			Some(UserId::new(5)),
+			Some(GuildId::new(3)),
+		),
+	)
+	.unwrap();
+}
+
+fn counter(db: &Database) -> u64 { db.state().progress(Some(GuildId::new(3))).counter }
+
+/// Opens the database, adds `events` counts, and closes it again
+fn open_and_count(backend: StorageBackend, path: &Path, events: usize) {
+	let mut db = Database::open(backend, path).unwrap();
+
+	for _ in 0..events {
+		count(&mut db);
+	}
+}
+
+#[test]
+fn opening_rotates_backups() {
+	let dir = tempfile::tempdir().unwrap();
+	let path = dir.path().join("db.jsonl");
+
+	for _ in 0..get_backup_count() + 2 {
+		open_and_count(StorageBackend::Journal, &path, 1);
+	}
+
+	assert_eq!(backup::list(&path).unwrap().len(), get_backup_count());
+}
+
+#[test]
+fn corrupt_journal_falls_back_to_a_backup() {
+	let dir = tempfile::tempdir().unwrap();
+	let path = dir.path().join("db.jsonl");
+
+	open_and_count(StorageBackend::Journal, &path, 3);
+	// The backup taken here has all 3 events
+	open_and_count(StorageBackend::Journal, &path, 2);
+
+	let journal = std::fs::read_to_string(&path).unwrap();
+	std::fs::write(&path, journal.replacen("Counter", "Garbage", 1)).unwrap();
+
+	let db = Database::open(StorageBackend::Journal, &path).unwrap();
+
+	assert_eq!(db.len(), 3);
+	assert_eq!(counter(&db), 3);
+
+	// The corrupt journal is kept around
+	let aside = std::fs::read_dir(dir.path())
+		.unwrap()
+		.map(|x| x.unwrap().file_name().to_string_lossy().to_string())
+		.find(|x| x.starts_with("db.jsonl.corrupt-"))
+		.unwrap();
+
+	assert!(
+		std::fs::read_to_string(dir.path().join(aside).join("db.jsonl"))
+			.unwrap()
+			.contains("Garbage")
+	);
+}
+
+#[test]
+fn corrupt_sqlite_falls_back_to_a_backup() {
+	let dir = tempfile::tempdir().unwrap();
+	let path = dir.path().join("db.sqlite");
+
+	open_and_count(StorageBackend::Sqlite, &path, 3);
+	open_and_count(StorageBackend::Sqlite, &path, 2);
+
+	std::fs::write(&path, b"definitely not a database").unwrap();
+
+	let db = Database::open(StorageBackend::Sqlite, &path).unwrap();
+
+	assert_eq!(counter(&db), 3);
+}
+
+#[test]
+fn without_a_good_backup_the_error_is_kept() {
+	let dir = tempfile::tempdir().unwrap();
+	let path = dir.path().join("db.jsonl");
+
+	std::fs::write(&path, "not json\n").unwrap();
+
+	assert!(Database::open(StorageBackend::Journal, &path).is_err());
+	assert_eq!(std::fs::read_to_string(&path).unwrap(), "not json\n");
+}
+
+#[test]
+fn events_from_a_newer_build_do_not_fall_back() {
+	let dir = tempfile::tempdir().unwrap();
+	let path = dir.path().join("db.jsonl");
+
+	open_and_count(StorageBackend::Journal, &path, 3);
+	open_and_count(StorageBackend::Journal, &path, 2);
+
+	let future = format!(
+		"{{\"version\":{},\"event\":{{\"Counter\":{{\"user\":\"5\"}}}}}}\n",
+		schema::CURRENT_VERSION + 1
+	);
+	let journal = std::fs::read_to_string(&path).unwrap() + &future;
+	std::fs::write(&path, &journal).unwrap();
+
+	let err = Database::open(StorageBackend::Journal, &path).unwrap_err();
+
+	assert!(err.chain().any(|x| x.is::<schema::TooNew>()));
+
+	// Nothing was moved aside or restored
+	assert_eq!(std::fs::read_to_string(&path).unwrap(), journal);
+	assert!(!std::fs::read_dir(dir.path()).unwrap().any(|x| x
+		.unwrap()
+		.file_name()
+		.to_string_lossy()
+		.contains("corrupt")));
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use quicksilver::data::{
+	bus::{Committed, Subscriber},
+	envelope::{EventMeta, EventSource},
+	state::DBEvent,
+};
+use serenity::all::{GuildId, UserId};
+use std::sync::{Arc, Mutex};
+
+/// Remembers the index and the counter on either side of every event
+#[derive(Debug, Default)]
+struct Recorder {
+	seen: Arc<Mutex<Vec<(usize, u64, u64)>>>,
+}
+
+impl Subscriber for Recorder {
+	fn committed(&self, event: &Committed) {
+		let guild = event.envelope.meta.guild;
+
+		self.seen.lock().unwrap().push((
+			event.index,
+			event.before.progress(guild).counter,
+			event.after.progress(guild).counter,
+		));
+	}
+}
+
+#[test]
+fn subscribers_see_committed_events_with_both_states() {
+	let dir = tempfile::tempdir().unwrap();
+
+	let mut db = common::database(dir.path());
+
+	let recorder = Recorder::default();
+	let seen = Arc::clone(&recorder.seen);
+
+	db.subscribe(recorder);
+
+	let meta = EventMeta::now(
+		EventSource::Command("counter".to_string()),
+		Some(UserId::new(5)),
+		Some(GuildId::new(3)),
+	);
+
+	for _ in 0..2 {
+		db.add(
+			DBEvent::Counter {
+				user: UserId::new(5),
+			},
+			meta.clone(),
+		)
+		.unwrap();
+	}
+
+	db.add(DBEvent::Revert { index: 1 }, meta).unwrap();
+
+	// The revert is delivered with the state it rebuilt
+	assert_eq!(*seen.lock().unwrap(), vec![(0, 0, 1), (1, 1, 2), (2, 2, 1)]);
+}
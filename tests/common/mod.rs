@@ -0,0 +1,9 @@
+//! Helpers shared by the integration tests
+
+use quicksilver::data::{storage::file::FileStorage, Database};
+use std::path::Path;
+
+/// Opens the journal database `db.jsonl` in `dir`
+pub fn database(dir: &Path) -> Database {
+	Database::new(Box::new(FileStorage::open(&dir.join("db.jsonl")).unwrap())).unwrap()
+}
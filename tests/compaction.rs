@@ -0,0 +1,145 @@
+mod common;
+
+use quicksilver::data::{
+	envelope::{EventMeta, EventSource},
+	state::DBEvent,
+	storage::{file::FileStorage, sqlite::SqliteStorage},
+	Database,
+};
+use serenity::all::{GuildId, UserId};
+use std::path::{Path, PathBuf};
+
+const GUILD: GuildId = GuildId::new(3);
+
+fn count(db: &mut Database, times: u64) {
+	for user in 0..times {
+		db.add(
+			DBEvent::Counter {
+				user: UserId::new(20 + user),
+			},
+			EventMeta::now(EventSource::Unknown, None, Some(GUILD)),
+		)
+		.unwrap();
+	}
+}
+
+fn open(path: &Path) -> eyre::Result<Database> { Database::new(Box::new(FileStorage::open(path)?)) }
+
+fn counter(db: &Database) -> u64 { db.state().progress(Some(GUILD)).counter }
+
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+	PathBuf::from(path.to_str().unwrap().to_string() + suffix)
+}
+
+#[test]
+fn compacted_databases_load_and_replay() {
+	let dir = tempfile::tempdir().unwrap();
+	let path = dir.path().join("db.jsonl");
+
+	let mut db = open(&path).unwrap();
+	count(&mut db, 3);
+	db.compact().unwrap();
+	count(&mut db, 2);
+	drop(db);
+
+	let mut db = open(&path).unwrap();
+
+	assert_eq!((db.len(), db.timeline().len()), (5, 2));
+	assert_eq!(counter(&db), 5);
+
+	db.validate().unwrap();
+}
+
+#[test]
+fn losing_the_snapshot_mid_compaction_fails_loudly() {
+	let dir = tempfile::tempdir().unwrap();
+	let path = dir.path().join("db.jsonl");
+
+	let mut db = open(&path).unwrap();
+	count(&mut db, 3);
+	db.compact().unwrap();
+	drop(db);
+
+	// As if we crashed right after emptying the journal
+	std::fs::remove_file(sibling(&path, ".snapshot")).unwrap();
+	std::fs::remove_file(sibling(&path, ".base")).unwrap();
+
+	assert!(open(&path).is_err());
+}
+
+#[test]
+fn the_previous_compaction_snapshot_does_not_fit() {
+	let dir = tempfile::tempdir().unwrap();
+	let path = dir.path().join("db.jsonl");
+
+	let mut db = open(&path).unwrap();
+	count(&mut db, 3);
+	db.compact().unwrap();
+
+	let previous = std::fs::read(sibling(&path, ".snapshot")).unwrap();
+
+	count(&mut db, 2);
+	db.compact().unwrap();
+	drop(db);
+
+	// As if we crashed before the second compaction stored its snapshot
+	std::fs::write(sibling(&path, ".snapshot"), previous).unwrap();
+
+	assert!(open(&path).is_err());
+}
+
+#[test]
+fn sqlite_keeps_only_the_snapshots_it_loads() {
+	let dir = tempfile::tempdir().unwrap();
+	let path = dir.path().join("db.sqlite");
+
+	let mut db = Database::new(Box::new(SqliteStorage::open(&path).unwrap())).unwrap();
+
+	for _ in 0..3 {
+		count(&mut db, 2);
+		db.snapshot().unwrap();
+	}
+
+	db.compact().unwrap();
+
+	for _ in 0..3 {
+		count(&mut db, 2);
+		db.snapshot().unwrap();
+	}
+
+	drop(db);
+
+	let offsets = rusqlite::Connection::open(&path)
+		.unwrap()
+		.prepare("SELECT offset FROM snapshots ORDER BY offset")
+		.unwrap()
+		.query_map([], |row| row.get::<_, i64>(0))
+		.unwrap()
+		.collect::<Result<Vec<_>, _>>()
+		.unwrap();
+
+	assert_eq!(offsets, [6, 12]);
+
+	let db = Database::new(Box::new(SqliteStorage::open(&path).unwrap())).unwrap();
+
+	assert_eq!(counter(&db), 12);
+}
+
+#[test]
+fn history_replays_without_the_database() {
+	let dir = tempfile::tempdir().unwrap();
+
+	let mut db = common::database(dir.path());
+	count(&mut db, 3);
+	db.compact().unwrap();
+	count(&mut db, 2);
+
+	let history = db.history().unwrap();
+	count(&mut db, 1);
+
+	let mut visited = vec![];
+	history.replay(|idx, _, state| visited.push((idx, state.progress(Some(GUILD)).counter)));
+
+	assert_eq!(history.compacted, 3);
+	assert_eq!(visited, [(3, 4), (4, 5)]);
+}
@@ -0,0 +1,88 @@
+use quicksilver::data::{
+	daily::{drop_table, DailyReward},
+	envelope::{EventEnvelope, EventMeta, EventSource},
+	items::Rarity,
+	rng::Seed,
+	state::{DBEvent, DBState, ReduceError},
+};
+use serenity::all::{GuildId, Timestamp, UserId};
+
+const GUILD: GuildId = GuildId::new(1);
+const USER: UserId = UserId::new(11);
+const DAY: i64 = 24 * 60 * 60;
+
+fn claim(state: &DBState, day: i64, seed: &str) -> Result<DBState, ReduceError> {
+	EventEnvelope {
+		meta: EventMeta::now(EventSource::Unknown, Some(USER), Some(GUILD))
+			.at(Timestamp::from_unix_timestamp(day * DAY + 12 * 60 * 60).unwrap()),
+		event: DBEvent::DailyClaim {
+			user: USER,
+			seed: seed.parse().unwrap(),
+		},
+	}
+	.reduce_state(state)
+}
+
+fn streak(state: &DBState) -> u64 { state.get_user_or_default(Some(GUILD), &USER).daily.streak }
+
+#[test]
+fn claims_give_xp_and_an_item() {
+	let state = claim(&DBState::default(), 100, "1").unwrap();
+
+	let user = state.get_user_or_default(Some(GUILD), &USER);
+	let reward = DailyReward::roll(1, &"1".parse::<Seed>().unwrap());
+
+	assert_eq!(user.items, [reward.item]);
+	assert_eq!(user.total_xp, reward.xp);
+	assert_eq!(reward.xp, 50);
+}
+
+#[test]
+fn only_one_claim_a_day() {
+	let state = claim(&DBState::default(), 100, "1").unwrap();
+
+	assert!(matches!(
+		claim(&state, 100, "2"),
+		Err(ReduceError::AlreadyClaimedDaily)
+	));
+}
+
+#[test]
+fn streaks_survive_a_grace_day_but_not_two() {
+	let state = claim(&DBState::default(), 100, "1").unwrap();
+	let state = claim(&state, 101, "2").unwrap();
+	assert_eq!(streak(&state), 2);
+
+	// Missing one day is forgiven
+	let state = claim(&state, 103, "3").unwrap();
+	assert_eq!(streak(&state), 3);
+
+	// Missing two isn't
+	let state = claim(&state, 106, "4").unwrap();
+	assert_eq!(streak(&state), 1);
+}
+
+#[test]
+fn longer_streaks_give_more() {
+	let seed = "5".parse::<Seed>().unwrap();
+
+	assert!(DailyReward::roll(7, &seed).xp > DailyReward::roll(1, &seed).xp);
+	assert_eq!(
+		DailyReward::roll(100, &seed).xp,
+		DailyReward::roll(14, &seed).xp
+	);
+
+	let rare_weight = |streak| {
+		let table = drop_table(streak);
+		let total = table.iter().map(|x| x.1).sum::<u64>();
+		let rare = table
+			.iter()
+			.filter(|x| !matches!(x.0.info().rarity, Rarity::Common))
+			.map(|x| x.1)
+			.sum::<u64>();
+
+		rare as f64 / total as f64
+	};
+
+	assert!(rare_weight(14) > rare_weight(1));
+}
@@ -0,0 +1,49 @@
+//! Replays fixture timelines and compares the state they end up in with the
+//! one stored next to them
+//!
+//! After a change that's meant to alter the result, run with `UPDATE_GOLDEN=1`
+//! to store the new states, and review the diff.
+
+mod common;
+
+use serde_json::Value;
+use std::path::Path;
+
+fn check(fixture: &str, expected: &str) {
+	let dir = tempfile::tempdir().unwrap();
+
+	// Fixtures are in the old `db.json` format, so this also covers migrating
+	std::fs::copy(
+		Path::new("tests/fixtures").join(fixture),
+		dir.path().join("db.json"),
+	)
+	.unwrap();
+
+	let db = common::database(dir.path());
+
+	// Objects compare without caring for key order, so maps can stay hash maps
+	let actual = serde_json::to_value(db.state()).unwrap();
+	let expected_path = Path::new("tests/fixtures").join(expected);
+
+	if std::env::var("UPDATE_GOLDEN").is_ok() {
+		std::fs::write(
+			&expected_path,
+			serde_json::to_string_pretty(&actual).unwrap() + "\n",
+		)
+		.unwrap();
+	}
+
+	let expected: Value =
+		serde_json::from_str(&std::fs::read_to_string(expected_path).unwrap()).unwrap();
+
+	assert_eq!(
+		actual, expected,
+		"{fixture} no longer replays to {expected}"
+	);
+}
+
+#[test]
+fn v0_replays_to_golden_state() { check("v0.json", "v0.state.json"); }
+
+#[test]
+fn mixed_timeline_replays_to_golden_state() { check("golden.json", "golden.state.json"); }
@@ -0,0 +1,115 @@
+use quicksilver::data::{
+	envelope::{EventEnvelope, EventMeta, EventSource},
+	leaderboard::{pages, rank, LeaderboardScope, PAGE_SIZE},
+	state::{DBEvent, DBState},
+	user::week,
+};
+use serenity::all::{GuildId, Timestamp, UserId};
+
+const GUILD: GuildId = GuildId::new(1);
+
+/// Monday the 7th of October 2024, at midnight UTC
+const MONDAY: i64 = 1_728_259_200;
+const DAY: i64 = 24 * 60 * 60;
+
+fn at(seconds: i64) -> Timestamp { Timestamp::from_unix_timestamp(seconds).unwrap() }
+
+/// `user` sends a message worth 15 XP in `guild` at `seconds`
+fn message(state: &DBState, guild: Option<GuildId>, user: u64, seconds: i64) -> DBState {
+	EventEnvelope {
+		meta: EventMeta::now(EventSource::Unknown, None, guild).at(at(seconds)),
+		event: DBEvent::UserSendMessage {
+			user: UserId::new(user),
+			length: 100,
+			fingerprint: None,
+			legacy_rules: true,
+		},
+	}
+	.reduce_state(state)
+	.unwrap()
+}
+
+/// Sends `count` messages each from `users`, taking turns so none are ignored
+fn messages(
+	state: DBState,
+	guild: Option<GuildId>,
+	users: &[u64],
+	count: usize,
+	seconds: i64,
+) -> DBState {
+	(0..count).fold(state, |state, _| {
+		users
+			.iter()
+			.fold(state, |state, user| message(&state, guild, *user, seconds))
+	})
+}
+
+fn ids(ranked: &[(UserId, quicksilver::data::user::DBUser)]) -> Vec<u64> {
+	ranked.iter().map(|(id, _)| id.get()).collect()
+}
+
+#[test]
+fn weeks_start_on_monday() {
+	assert_eq!(week(&at(MONDAY)), week(&at(MONDAY + 7 * DAY - 1)));
+	assert_eq!(week(&at(MONDAY)) + 1, week(&at(MONDAY + 7 * DAY)));
+	assert_eq!(week(&at(MONDAY)) - 1, week(&at(MONDAY - 1)));
+}
+
+#[test]
+fn ranks_by_level_then_xp() {
+	let state = messages(DBState::default(), Some(GUILD), &[1, 2], 10, MONDAY);
+	let state = messages(state, Some(GUILD), &[3, 2], 1, MONDAY);
+
+	let guild = rank(&state, Some(GUILD), LeaderboardScope::Guild, &at(MONDAY));
+
+	// 2 is ahead of 1 by one message, 3 only sent one
+	assert_eq!(ids(&guild), [2, 1, 3]);
+}
+
+#[test]
+fn global_scope_adds_up_every_guild() {
+	let other = GuildId::new(2);
+
+	let state = messages(DBState::default(), Some(GUILD), &[11, 12], 10, MONDAY);
+	let state = messages(state, Some(other), &[13, 11], 2, MONDAY);
+	let state = messages(state, None, &[14, 15], 1, MONDAY);
+
+	let global = rank(&state, None, LeaderboardScope::Global, &at(MONDAY));
+
+	// 11 has 150 XP here and 30 in the other guild, 12 only has 150
+	assert_eq!(ids(&global), [11, 12, 13, 14, 15]);
+	assert_eq!(global[0].1.total_xp, 180);
+}
+
+#[test]
+fn weekly_scope_only_counts_this_week() {
+	let last_week = messages(DBState::default(), Some(GUILD), &[1, 2], 20, MONDAY - DAY);
+	let state = messages(last_week, Some(GUILD), &[3, 2], 2, MONDAY + DAY);
+
+	let ranked = rank(
+		&state,
+		Some(GUILD),
+		LeaderboardScope::Week,
+		&at(MONDAY + 2 * DAY),
+	);
+
+	// 3 and 2 sent as much this week, ties go by id
+	assert_eq!(ids(&ranked), [2, 3]);
+	assert_eq!(ranked[0].1.weekly_xp, 30);
+
+	let next_week = rank(
+		&state,
+		Some(GUILD),
+		LeaderboardScope::Week,
+		&at(MONDAY + 8 * DAY),
+	);
+
+	assert!(next_week.is_empty());
+}
+
+#[test]
+fn there_is_always_a_page() {
+	assert_eq!(pages(0), 1);
+	assert_eq!(pages(PAGE_SIZE), 1);
+	assert_eq!(pages(PAGE_SIZE + 1), 2);
+}
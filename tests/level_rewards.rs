@@ -0,0 +1,173 @@
+mod common;
+
+use quicksilver::{
+	data::{
+		envelope::{EventMeta, EventSource},
+		leveling::{LevelCurve, Leveling},
+		state::DBEvent,
+		Database,
+	},
+	systems::{
+		autoconfig::data::{level_reward_role, role},
+		level_rewards::{reward_role_changes, RoleChange},
+	},
+};
+use serenity::all::{GuildId, RoleId, UserId};
+
+const GUILD: GuildId = GuildId::new(1);
+
+fn add(db: &mut Database, event: DBEvent) {
+	db.add(
+		event,
+		EventMeta::now(EventSource::Unknown, None, Some(GUILD)),
+	)
+	.unwrap();
+}
+
+fn reward(db: &mut Database, level: u64, name: &str) {
+	add(
+		db,
+		DBEvent::LevelRewardAdd {
+			server: GUILD,
+			level,
+			name: name.to_string(),
+		},
+	);
+}
+
+#[test]
+fn rewards_become_roles_in_level_order() {
+	let dir = tempfile::tempdir().unwrap();
+	let mut db = common::database(dir.path());
+
+	reward(&mut db, 10, "Wanderer");
+	reward(&mut db, 5, "Traveller");
+	reward(&mut db, 20, "Explorer");
+	add(
+		&mut db,
+		DBEvent::LevelRewardForget {
+			server: GUILD,
+			level: 20,
+		},
+	);
+
+	let config = db.get_config(&GUILD);
+
+	assert_eq!(
+		config.roles[&level_reward_role(10)].name,
+		"Level 10 — Wanderer"
+	);
+	assert!(!config.roles.contains_key(&level_reward_role(20)));
+
+	// Above the places, with higher levels first
+	let order = config
+		.role_order
+		.iter()
+		.position(|x| *x == level_reward_role(10))
+		.unwrap();
+
+	assert_eq!(config.role_order[order + 1], level_reward_role(5));
+	assert!(config.role_order[order + 2].0.starts_with("places/"));
+	assert!(!config.role_order.contains(&role("levels/20")));
+}
+
+#[test]
+fn only_made_roles_for_reached_levels_are_given() {
+	let dir = tempfile::tempdir().unwrap();
+	let mut db = common::database(dir.path());
+
+	for (level, name) in [(5, "Traveller"), (10, "Wanderer"), (20, "Explorer")] {
+		reward(&mut db, level, name);
+	}
+
+	// Autoconfig hasn't made the role for level 20 yet
+	for (level, id) in [(5, 105), (10, 110)] {
+		add(
+			&mut db,
+			DBEvent::RoleAdd {
+				server: GUILD,
+				id: level_reward_role(level),
+				discord_id: RoleId::new(id),
+			},
+		);
+	}
+
+	let server = db.state().get_server_or_default(&GUILD);
+
+	assert_eq!(
+		server.level_reward_roles(2..=10),
+		[RoleId::new(105), RoleId::new(110)]
+	);
+	assert!(server.level_reward_roles(6..=9).is_empty());
+	assert!(server.level_reward_roles(11..=30).is_empty());
+}
+
+#[test]
+fn roles_follow_levels_up_and_down_for_everyone() {
+	let dir = tempfile::tempdir().unwrap();
+	let mut db = common::database(dir.path());
+
+	for (level, id) in [(2, 102), (5, 105)] {
+		reward(&mut db, level, "Reward");
+		add(
+			&mut db,
+			DBEvent::RoleAdd {
+				server: GUILD,
+				id: level_reward_role(level),
+				discord_id: RoleId::new(id),
+			},
+		);
+	}
+
+	// Taking turns, so none of the messages are ignored
+	for _ in 0..10 {
+		for user in [11, 12] {
+			add(
+				&mut db,
+				DBEvent::UserSendMessage {
+					user: UserId::new(user),
+					length: 200,
+					fingerprint: None,
+					legacy_rules: true,
+				},
+			);
+		}
+	}
+
+	let change = |user, add: &[u64], remove: &[u64]| RoleChange {
+		guild: GUILD,
+		user: UserId::new(user),
+		add: add.iter().copied().map(RoleId::new).collect(),
+		remove: remove.iter().copied().map(RoleId::new).collect(),
+	};
+
+	// A flatter curve raises everyone's level, without any of them doing anything
+	let before = db.state().clone();
+	add(
+		&mut db,
+		DBEvent::SetLeveling {
+			server: GUILD,
+			leveling: Leveling {
+				curve: LevelCurve::Linear { base: 10, step: 0 },
+				..Default::default()
+			},
+		},
+	);
+
+	assert_eq!(
+		before.progress(Some(GUILD)).users[&UserId::new(11)].level,
+		2
+	);
+	assert_eq!(
+		reward_role_changes(&before, db.state(), GUILD, None),
+		[change(11, &[105], &[]), change(12, &[105], &[])]
+	);
+
+	let before = db.state().clone();
+	add(&mut db, DBEvent::EndSeason { rewards: vec![] });
+
+	assert_eq!(
+		reward_role_changes(&before, db.state(), GUILD, None),
+		[change(11, &[], &[102, 105]), change(12, &[], &[102, 105])]
+	);
+}
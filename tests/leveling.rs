@@ -0,0 +1,270 @@
+use quicksilver::data::{
+	envelope::{EventEnvelope, EventMeta, EventSource},
+	leveling::{CurveError, LevelCurve, Leveling, MessageXp, MAX_LEVEL},
+	schema::decode_state,
+	state::{DBEvent, DBState},
+	user::DBUser,
+};
+use serenity::all::{GuildId, UserId};
+
+const GUILD: GuildId = GuildId::new(1);
+const USER: UserId = UserId::new(2);
+
+fn apply(state: &DBState, event: DBEvent) -> DBState {
+	EventEnvelope {
+		meta: EventMeta::now(EventSource::Unknown, None, Some(GUILD)),
+		event,
+	}
+	.reduce_state(state)
+	.unwrap()
+}
+
+/// A message from [`USER`], after someone else talks so it isn't ignored
+fn message(state: &DBState, length: usize) -> DBState {
+	[UserId::new(3), USER]
+		.into_iter()
+		.fold(state.clone(), |state, user| {
+			apply(
+				&state,
+				DBEvent::UserSendMessage {
+					user,
+					length,
+					fingerprint: None,
+					legacy_rules: true,
+				},
+			)
+		})
+}
+
+#[test]
+fn defaults_match_the_old_formulas() {
+	let curve = LevelCurve::default();
+
+	for level in 1..1000 {
+		assert_eq!(curve.required(level), 100 + level.pow(2) / 2);
+	}
+
+	let message = MessageXp::default();
+
+	for length in 0..100 {
+		let old = ((length as f64 / 15f64).powf(2f64) * 5f64)
+			.min(15f64)
+			.round() as u64;
+
+		assert_eq!(message.xp(length), old);
+	}
+}
+
+#[test]
+fn curves_parse_and_print() {
+	for text in [
+		"linear 100 10",
+		"polynomial 100 1 2 2",
+		"exponential 100 10",
+		"table 50 100 200",
+	] {
+		assert_eq!(text.parse::<LevelCurve>().unwrap().to_string(), text);
+	}
+
+	assert_eq!("table".parse::<LevelCurve>(), Err(CurveError::EmptyTable));
+	assert_eq!(
+		"polynomial 1 1 1 0".parse::<LevelCurve>(),
+		Err(CurveError::ZeroDivisor)
+	);
+	assert_eq!(
+		"linear 100".parse::<LevelCurve>(),
+		Err(CurveError::Malformed)
+	);
+	assert_eq!(
+		"cubic 1 2".parse::<LevelCurve>(),
+		Err(CurveError::Malformed)
+	);
+	assert_eq!(
+		"linear 1 0".parse::<LevelCurve>(),
+		Err(CurveError::TooCheap)
+	);
+	assert_eq!(
+		"table 100 5 200".parse::<LevelCurve>(),
+		Err(CurveError::TooCheap)
+	);
+}
+
+#[test]
+fn curves_give_the_expected_requirements() {
+	let linear = LevelCurve::Linear {
+		base: 100,
+		step: 10,
+	};
+	assert_eq!(
+		(1..=3).map(|x| linear.required(x)).collect::<Vec<_>>(),
+		[100, 110, 120]
+	);
+
+	let exponential = LevelCurve::Exponential {
+		base: 100,
+		percent: 10,
+	};
+	assert_eq!(
+		(1..=3).map(|x| exponential.required(x)).collect::<Vec<_>>(),
+		[100, 110, 121]
+	);
+
+	// The last level of a table repeats, and nothing is ever free
+	let table = LevelCurve::Table(vec![0, 20]);
+	assert_eq!(
+		(1..=3).map(|x| table.required(x)).collect::<Vec<_>>(),
+		[1, 20, 20]
+	);
+}
+
+#[test]
+fn changing_the_curve_recomputes_levels_from_lifetime_xp() {
+	let mut state = DBState::default();
+
+	for _ in 0..30 {
+		state = message(&state, 100);
+	}
+
+	let before = state.get_user_or_default(Some(GUILD), &USER);
+	assert_eq!(before.total_xp, 30 * 15);
+	assert_eq!(before.level, 5);
+
+	let state = apply(
+		&state,
+		DBEvent::SetLeveling {
+			server: GUILD,
+			leveling: Leveling {
+				curve: LevelCurve::Linear { base: 50, step: 0 },
+				..Default::default()
+			},
+		},
+	);
+
+	let after = state.get_user_or_default(Some(GUILD), &USER);
+	assert_eq!(after.total_xp, before.total_xp);
+	assert_eq!((after.level, after.this_levels_xp), (10, 0));
+	assert_eq!(after.xp_until_next_level, 50);
+
+	// And new XP follows the new curve
+	let state = message(&state, 100);
+	assert_eq!(
+		state.get_user_or_default(Some(GUILD), &USER).this_levels_xp,
+		15
+	);
+}
+
+#[test]
+fn lifetime_xp_is_worked_out_for_old_states() {
+	let state = decode_state(serde_json::json!({
+		"global": {
+			"counter": 0,
+			"people_who_counted": [],
+			"flips_in_a_row": 0,
+			"users": {
+				"2": {
+					"this_levels_xp": 7,
+					"xp_until_next_level": 104,
+					"level": 3,
+					"items": [],
+					"life": { "health": 150, "max_health": 150 }
+				}
+			},
+			"last_typed_user": 1
+		},
+		"servers": {}
+	}))
+	.unwrap();
+
+	assert_eq!(
+		state.get_user_or_default(None, &USER).total_xp,
+		100 + 102 + 7
+	);
+}
+
+#[test]
+fn requirements_match_required_level_by_level() {
+	for curve in [
+		LevelCurve::default(),
+		LevelCurve::Exponential {
+			base: 100,
+			percent: 10,
+		},
+		LevelCurve::Exponential {
+			base: 0,
+			percent: 50,
+		},
+		LevelCurve::Table(vec![10, 20]),
+	] {
+		assert_eq!(
+			curve.requirements(3).take(50).collect::<Vec<_>>(),
+			(3..53).map(|x| curve.required(x)).collect::<Vec<_>>(),
+			"{curve}"
+		);
+	}
+}
+
+#[test]
+fn cheap_exponential_curves_recompute_quickly() {
+	let mut user = DBUser {
+		total_xp: 100_000,
+		..Default::default()
+	};
+
+	// Every level costs 1 XP, so this would be 100k levels without the cap
+	user.recompute_level(&LevelCurve::Exponential {
+		base: 1,
+		percent: 0,
+	});
+
+	assert_eq!(
+		(user.level, user.this_levels_xp),
+		(MAX_LEVEL, 100_000 - (MAX_LEVEL - 1))
+	);
+}
+
+#[test]
+fn leaving_the_global_profile_catches_up_with_the_curve() {
+	let global_profile = |state: &DBState, enabled| {
+		apply(
+			state,
+			DBEvent::SetGlobalProfile {
+				server: GUILD,
+				enabled,
+			},
+		)
+	};
+
+	let mut state = DBState::default();
+
+	for _ in 0..30 {
+		state = message(&state, 100);
+	}
+
+	let state = global_profile(&state, true);
+	let state = apply(
+		&state,
+		DBEvent::SetLeveling {
+			server: GUILD,
+			leveling: Leveling {
+				curve: LevelCurve::Linear { base: 50, step: 0 },
+				..Default::default()
+			},
+		},
+	);
+	let state = global_profile(&state, false);
+
+	let user = state.get_user_or_default(Some(GUILD), &USER);
+	assert_eq!((user.level, user.xp_until_next_level), (10, 50));
+}
+
+#[test]
+fn huge_xp_saturates_and_stops_at_the_last_level() {
+	let mut user = DBUser::default();
+	let curve = LevelCurve::Linear { base: 1, step: 0 };
+
+	user.gain_xp(u64::MAX, &curve);
+	user.gain_xp(u64::MAX, &curve);
+
+	assert_eq!(user.total_xp, u64::MAX);
+	assert_eq!(user.level, MAX_LEVEL);
+}
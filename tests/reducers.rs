@@ -0,0 +1,178 @@
+use proptest::prelude::*;
+use quicksilver::{
+	data::{
+		battle::LivingBuilder,
+		envelope::{EventEnvelope, EventMeta, EventSource},
+		items::InventoryItem,
+		leveling::LevelCurve,
+		rng::Flip,
+		state::{DBEvent, DBState},
+		user::DBUser,
+	},
+	utils::calculate_length_to_xp,
+};
+use serenity::all::{GuildId, UserId};
+use std::collections::HashMap;
+
+const USERS: [u64; 3] = [5, 6, 7];
+const GUILDS: [Option<u64>; 3] = [None, Some(3), Some(4)];
+
+fn item() -> impl Strategy<Value = InventoryItem> {
+	prop_oneof![Just(InventoryItem::Stick), Just(InventoryItem::ScytheVivi)]
+}
+
+fn event() -> impl Strategy<Value = DBEvent> {
+	let user = prop::sample::select(USERS.to_vec()).prop_map(UserId::new);
+
+	prop_oneof![
+		user.clone().prop_map(|user| DBEvent::Counter { user }),
+		any::<bool>().prop_map(|heads| DBEvent::CoinFlip {
+			flip: Flip::Legacy { heads }
+		}),
+		(
+			user.clone(),
+			0..400usize,
+			any::<Option<u64>>(),
+			any::<bool>()
+		)
+			.prop_map(
+				|(user, length, fingerprint, legacy_rules)| DBEvent::UserSendMessage {
+					user,
+					length,
+					fingerprint,
+					legacy_rules,
+				}
+			),
+		(user.clone(), item()).prop_map(|(user, item)| DBEvent::AdminGive { user, item }),
+		(user, item()).prop_map(|(user, item)| DBEvent::AdminBurn { user, item }),
+	]
+}
+
+/// A timeline of events spread over a few guilds
+fn timeline() -> impl Strategy<Value = Vec<EventEnvelope>> {
+	prop::collection::vec((event(), prop::sample::select(GUILDS.to_vec())), 0..200).prop_map(
+		|events| {
+			events
+				.into_iter()
+				.map(|(event, guild)| EventEnvelope {
+					meta: EventMeta::now(EventSource::Unknown, None, guild.map(GuildId::new)),
+					event,
+				})
+				.collect()
+		},
+	)
+}
+
+fn users(state: &DBState) -> impl Iterator<Item = ((Option<GuildId>, UserId), &DBUser)> {
+	let guilds = state.servers.iter().flat_map(|(guild, server)| {
+		server
+			.progress
+			.users
+			.iter()
+			.map(|(id, x)| ((Some(*guild), *id), x))
+	});
+
+	state
+		.global
+		.users
+		.iter()
+		.map(|(id, x)| ((None, *id), x))
+		.chain(guilds)
+}
+
+proptest! {
+	#[test]
+	fn reducers_keep_users_consistent(timeline in timeline()) {
+		let mut state = DBState::default();
+		let mut levels = HashMap::new();
+		let mut held = HashMap::<_, i64>::new();
+
+		for envelope in &timeline {
+			let key = |user: &UserId| (envelope.meta.guild, *user);
+			let result = envelope.reduce_state(&state);
+
+			match &envelope.event {
+				DBEvent::AdminGive { user, item } => {
+					*held.entry((key(user), *item)).or_default() += 1;
+				}
+				DBEvent::AdminBurn { user, item } => {
+					let count = held.entry((key(user), *item)).or_default();
+
+					// Burning fails exactly when there's nothing to burn
+					prop_assert_eq!(result.is_err(), *count == 0);
+
+					if *count > 0 {
+						*count -= 1;
+					}
+				}
+				_ => prop_assert!(result.is_ok()),
+			}
+
+			if let Ok(next) = result {
+				state = next;
+			}
+
+			for (key, user) in users(&state) {
+				let level = levels.entry(key).or_insert(user.level);
+
+				prop_assert!(user.level >= *level, "level went down");
+				*level = user.level;
+
+				prop_assert!(user.this_levels_xp < user.xp_until_next_level);
+				prop_assert!(user.life.health() <= user.life.max_health());
+			}
+		}
+
+		for (((guild, user), item), count) in held {
+			let items = &state.get_user_or_default(guild, &user).items;
+
+			prop_assert_eq!(items.iter().filter(|x| **x == item).count() as i64, count);
+		}
+	}
+
+	#[test]
+	fn gaining_xp_never_loses_any(gains in prop::collection::vec(0..1000u64, 0..50)) {
+		let mut user = DBUser::default();
+		let mut total = 0;
+
+		for xp in gains {
+			user.gain_xp(xp, &LevelCurve::default());
+			total += xp;
+
+			prop_assert!(user.this_levels_xp < user.xp_until_next_level);
+
+			// Every level before this one took `100 + level^2 / 2` xp
+			let spent: u64 = (1..user.level).map(|x| 100 + x.pow(2) / 2).sum();
+
+			prop_assert_eq!(spent + user.this_levels_xp, total);
+			prop_assert_eq!(user.total_xp, total);
+		}
+	}
+
+	#[test]
+	fn health_stays_in_bounds(
+		max in 0..1000u32,
+		changes in prop::collection::vec((any::<bool>(), any::<u32>()), 0..50),
+	) {
+		let mut life = LivingBuilder::new().health(max).build().unwrap();
+
+		for (heal, amount) in changes {
+			if heal {
+				life.heal(amount);
+			} else {
+				life.damage(amount);
+			}
+
+			prop_assert!(life.health() <= life.max_health());
+			prop_assert_eq!(life.max_health(), max);
+		}
+	}
+
+	#[test]
+	fn longer_messages_never_give_less_xp(a in 0..10_000usize, b in 0..10_000usize) {
+		let (short, long) = (a.min(b), a.max(b));
+
+		prop_assert!(calculate_length_to_xp(&short) <= calculate_length_to_xp(&long));
+		prop_assert!(calculate_length_to_xp(&long) <= 15);
+	}
+}
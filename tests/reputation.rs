@@ -0,0 +1,94 @@
+use quicksilver::data::{
+	envelope::{EventEnvelope, EventMeta, EventSource},
+	leaderboard::{rank, LeaderboardScope},
+	reputation::{RepError, RepSource, COOLDOWN, DAILY_BUDGET},
+	state::{DBEvent, DBState, ReduceError},
+};
+use serenity::all::{GuildId, Timestamp, UserId};
+
+const GUILD: GuildId = GuildId::new(1);
+const START: i64 = 1_700_000_000;
+
+fn rep(state: &DBState, giver: u64, receiver: u64, at: i64) -> Result<DBState, ReduceError> {
+	EventEnvelope {
+		meta: EventMeta::now(EventSource::Unknown, Some(UserId::new(giver)), Some(GUILD))
+			.at(Timestamp::from_unix_timestamp(at).unwrap()),
+		event: DBEvent::GiveReputation {
+			giver: UserId::new(giver),
+			receiver: UserId::new(receiver),
+			source: RepSource::Command,
+		},
+	}
+	.reduce_state(state)
+}
+
+fn reputation(state: &DBState, user: u64) -> u64 {
+	state
+		.get_user_or_default(Some(GUILD), &UserId::new(user))
+		.reputation
+}
+
+#[test]
+fn giving_rep_counts_for_the_receiver() {
+	let state = rep(&DBState::default(), 11, 12, START).unwrap();
+	let state = rep(&state, 13, 12, START).unwrap();
+
+	assert_eq!(reputation(&state, 12), 2);
+	assert_eq!(reputation(&state, 11), 0);
+}
+
+#[test]
+fn nobody_can_rep_themselves() {
+	assert!(matches!(
+		rep(&DBState::default(), 11, 11, START),
+		Err(ReduceError::Reputation(RepError::SelfRep))
+	));
+}
+
+#[test]
+fn the_same_member_can_only_be_thanked_after_the_cooldown() {
+	let state = rep(&DBState::default(), 11, 12, START).unwrap();
+
+	assert!(matches!(
+		rep(&state, 11, 12, START + COOLDOWN - 1),
+		Err(ReduceError::Reputation(RepError::Cooldown(_)))
+	));
+
+	let state = rep(&state, 11, 12, START + COOLDOWN).unwrap();
+
+	assert_eq!(reputation(&state, 12), 2);
+}
+
+#[test]
+fn givers_have_a_daily_budget() {
+	let state = (0..DAILY_BUDGET as u64).fold(DBState::default(), |state, x| {
+		rep(&state, 11, 20 + x, START).unwrap()
+	});
+
+	assert!(matches!(
+		rep(&state, 11, 30, START),
+		Err(ReduceError::Reputation(RepError::BudgetSpent))
+	));
+
+	// A new day brings a new budget
+	assert!(rep(&state, 11, 30, START + 24 * 60 * 60).is_ok());
+}
+
+#[test]
+fn the_reputation_leaderboard_ranks_the_most_thanked() {
+	let state = rep(&DBState::default(), 11, 12, START).unwrap();
+	let state = rep(&state, 13, 12, START).unwrap();
+	let state = rep(&state, 12, 14, START).unwrap();
+
+	let ranked = rank(
+		&state,
+		Some(GUILD),
+		LeaderboardScope::Reputation,
+		&Timestamp::now(),
+	);
+
+	assert_eq!(
+		ranked.iter().map(|x| x.0.get()).collect::<Vec<_>>(),
+		[12, 14]
+	);
+}
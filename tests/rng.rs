@@ -0,0 +1,165 @@
+use proptest::prelude::*;
+use quicksilver::data::{
+	envelope::{EventEnvelope, EventMeta, EventSource},
+	rng::{Dice, DiceError, FairFlip, Flip, Seed},
+	state::{DBEvent, DBState, ReduceError},
+};
+use serenity::all::{GuildId, UserId};
+
+fn seed(x: &str) -> Seed { x.parse().unwrap() }
+
+#[test]
+fn fair_flips_follow_the_documented_rule() {
+	let seed = seed("ff");
+
+	// Values from `printf ... | sha256sum`
+	assert_eq!(
+		seed.commitment(),
+		"1621af0b22457bcd05e278d1878bd73e3d83f17134285c575e551f8ddd9e91d3"
+	);
+
+	let flip = |client_seed: &str| FairFlip {
+		seed,
+		client_seed: client_seed.to_string(),
+		next: seed,
+	};
+
+	assert!(flip("1234").hash().starts_with("0b392ebe"));
+	assert!(flip("1234").heads());
+
+	assert!(flip("99").hash().starts_with("e4c39faf"));
+	assert!(!flip("99").heads());
+}
+
+#[test]
+fn flips_have_to_use_the_committed_seed() {
+	let flip = |seed: &str, next: &str| EventEnvelope {
+		meta: EventMeta::now(
+			EventSource::Command("coinflip".to_string()),
+			Some(UserId::new(5)),
+			Some(GuildId::new(3)),
+		),
+		event: DBEvent::CoinFlip {
+			flip: Flip::Fair(FairFlip {
+				seed: self::seed(seed),
+				client_seed: "1".to_string(),
+				next: self::seed(next),
+			}),
+		},
+	};
+
+	let state = flip("1", "2").reduce_state(&DBState::default()).unwrap();
+
+	assert!(matches!(
+		flip("3", "4").reduce_state(&state),
+		Err(ReduceError::UncommittedSeed)
+	));
+
+	assert!(flip("2", "3").reduce_state(&state).is_ok());
+}
+
+#[test]
+fn parses_dice() {
+	let dice = |count, sides, modifier| Dice {
+		count,
+		sides,
+		modifier,
+	};
+
+	assert_eq!("d20".parse(), Ok(dice(1, 20, 0)));
+	assert_eq!("3d6".parse(), Ok(dice(3, 6, 0)));
+	assert_eq!("2D8+4".parse(), Ok(dice(2, 8, 4)));
+	assert_eq!(" 4d10-1 ".parse(), Ok(dice(4, 10, -1)));
+
+	assert_eq!("20".parse::<Dice>(), Err(DiceError::Malformed));
+	assert_eq!("2d".parse::<Dice>(), Err(DiceError::Malformed));
+	assert_eq!("1d6+x".parse::<Dice>(), Err(DiceError::Malformed));
+	assert_eq!("1000d6".parse::<Dice>(), Err(DiceError::BadCount));
+	assert_eq!("0d6".parse::<Dice>(), Err(DiceError::BadCount));
+	assert_eq!("1d0".parse::<Dice>(), Err(DiceError::BadSides));
+	assert_eq!(
+		"d6+9223372036854775807".parse::<Dice>(),
+		Err(DiceError::BadModifier)
+	);
+	assert_eq!("d6-1000001".parse::<Dice>(), Err(DiceError::BadModifier));
+
+	assert_eq!(dice(2, 8, -4).to_string(), "2d8-4");
+	assert_eq!("d20".parse::<Dice>().unwrap().to_string(), "1d20");
+}
+
+proptest! {
+	#[test]
+	fn seeds_replay_the_same(x in any::<u64>()) {
+		let seed = seed(&format!("{x:x}"));
+
+		let (mut a, mut b) = (seed.rng(), seed.rng());
+
+		for _ in 0..10 {
+			prop_assert_eq!(a.next_u64(), b.next_u64());
+		}
+
+		let json = serde_json::to_string(&seed).unwrap();
+		prop_assert_eq!(serde_json::from_str::<Seed>(&json).unwrap(), seed);
+	}
+
+	#[test]
+	fn ranges_stay_in_range(x in any::<u64>(), start in 0..1000u64, len in 1..1000u64) {
+		let mut rng = seed(&format!("{x:x}")).rng();
+
+		for _ in 0..100 {
+			prop_assert!((start..start + len).contains(&rng.range(start..start + len)));
+
+			let unit = rng.unit();
+			prop_assert!((0.0..1.0).contains(&unit));
+		}
+	}
+
+	#[test]
+	fn weighted_choice_skips_weightless_options(x in any::<u64>(), weights in prop::collection::vec(0..3u64, 1..10)) {
+		let mut rng = seed(&format!("{x:x}")).rng();
+		let options = weights.iter().copied().enumerate().collect::<Vec<_>>();
+
+		for _ in 0..20 {
+			match rng.choose_weighted(&options) {
+				Some(idx) => prop_assert!(weights[*idx] > 0),
+				None => prop_assert!(weights.iter().all(|x| *x == 0)),
+			}
+		}
+	}
+
+	#[test]
+	fn dice_rolls_stay_in_range(x in any::<u64>(), count in 1..20u32, sides in 1..100u64, modifier in -10..10i64) {
+		let dice = Dice { count, sides, modifier };
+		let roll = dice.roll(&mut seed(&format!("{x:x}")).rng());
+
+		prop_assert_eq!(roll.rolls.len(), count as usize);
+		prop_assert!(roll.rolls.iter().all(|x| (1..=sides).contains(x)));
+		prop_assert_eq!(roll.total, roll.rolls.iter().sum::<u64>() as i64 + modifier);
+	}
+}
+
+#[test]
+fn rolls_have_to_use_the_committed_seed() {
+	let roll = |seed: &str, next: &str| EventEnvelope {
+		meta: EventMeta::now(
+			EventSource::Command("roll".to_string()),
+			Some(UserId::new(5)),
+			Some(GuildId::new(3)),
+		),
+		event: DBEvent::DiceRoll {
+			user: UserId::new(5),
+			dice: "2d6+3".parse().unwrap(),
+			seed: self::seed(seed),
+			next: self::seed(next),
+		},
+	};
+
+	let state = roll("1", "2").reduce_state(&DBState::default()).unwrap();
+
+	assert!(matches!(
+		roll("3", "4").reduce_state(&state),
+		Err(ReduceError::UncommittedSeed)
+	));
+
+	assert!(roll("2", "3").reduce_state(&state).is_ok());
+}
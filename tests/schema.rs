@@ -0,0 +1,404 @@
+mod common;
+
+use quicksilver::{
+	config::get_legacy_guild,
+	data::{
+		envelope::{EventEnvelope, EventMeta, EventSource},
+		items::InventoryItem,
+		rng::Flip,
+		schema,
+		snapshot::Snapshot,
+		state::{DBEvent, DBState, ReduceError},
+		storage::sqlite::SqliteStorage,
+		Database,
+	},
+	systems::autoconfig::data::role,
+};
+use serenity::all::{ChannelId, GuildId, MessageId, RoleId, Timestamp, UserId};
+use std::path::Path;
+use tempfile::TempDir;
+
+/// Copies a fixture into a fresh directory as `file_name` and opens a journal
+/// database next to it
+fn open_fixture(fixture: &str, file_name: &str) -> (TempDir, Database) {
+	let dir = tempfile::tempdir().unwrap();
+
+	std::fs::copy(
+		Path::new("tests/fixtures").join(fixture),
+		dir.path().join(file_name),
+	)
+	.unwrap();
+
+	let db = common::database(dir.path());
+
+	(dir, db)
+}
+
+/// Every fixture holds the same timeline, just written by a different version,
+/// with progress kept in `guild`
+fn assert_fixture_state(db: &Database, guild: GuildId) {
+	assert_eq!(db.len(), 8);
+
+	assert_fixture_state_in(db.state(), guild);
+}
+
+fn assert_fixture_state_in(state: &DBState, guild: GuildId) {
+	let progress = state.progress(Some(guild));
+
+	assert_eq!(progress.counter, 1);
+	assert_eq!(progress.flips_in_a_row, 1);
+
+	let first = &progress.users[&UserId::new(5)];
+	assert_eq!(first.this_levels_xp, 15);
+	assert_eq!(first.items, vec![InventoryItem::Stick]);
+
+	let second = &progress.users[&UserId::new(6)];
+	assert_eq!(second.this_levels_xp, 5);
+
+	assert!(state.global.users.is_empty());
+
+	assert_eq!(
+		state.servers[&GuildId::new(3)].roles[&role("admin")],
+		RoleId::new(4)
+	);
+}
+
+#[test]
+fn loads_v0_json_array() {
+	let (dir, db) = open_fixture("v0.json", "db.json");
+
+	assert_fixture_state(&db, get_legacy_guild());
+
+	// The old file is moved out of the way once it's migrated
+	assert!(!dir.path().join("db.json").exists());
+	assert!(dir.path().join("db.json.migrated").exists());
+}
+
+#[test]
+fn loads_v0_journal() {
+	let (_dir, db) = open_fixture("v0.jsonl", "db.jsonl");
+
+	assert_fixture_state(&db, get_legacy_guild());
+}
+
+#[test]
+fn loads_v1_journal() {
+	let (_dir, db) = open_fixture("v1.jsonl", "db.jsonl");
+
+	assert_fixture_state(&db, get_legacy_guild());
+}
+
+#[test]
+fn loads_v2_journal() {
+	let (_dir, db) = open_fixture("v2.jsonl", "db.jsonl");
+
+	assert_fixture_state(&db, GuildId::new(3));
+
+	let give = &db.timeline()[3].meta;
+	assert_eq!(give.actor, Some(UserId::new(7)));
+	assert_eq!(give.guild, Some(GuildId::new(3)));
+	assert_eq!(give.source, EventSource::Command("admin_give".to_string()));
+	assert_eq!(
+		give.timestamp,
+		Timestamp::parse("2024-06-20T12:03:00Z").unwrap()
+	);
+}
+
+#[test]
+fn loads_v3_journal() {
+	let (_dir, db) = open_fixture("v3.jsonl", "db.jsonl");
+
+	assert_fixture_state(&db, GuildId::new(3));
+
+	// Channels came after version 3
+	assert!(db.timeline().iter().all(|x| x.meta.channel.is_none()));
+}
+
+#[test]
+fn loads_v4_journal() {
+	let (_dir, db) = open_fixture("v4.jsonl", "db.jsonl");
+
+	assert_fixture_state(&db, GuildId::new(3));
+
+	assert_eq!(db.timeline()[0].meta.channel, Some(ChannelId::new(9)));
+	assert_eq!(db.timeline()[7].meta.channel, None);
+
+	// Flips from before they were provably fair keep just the outcome
+	assert!(matches!(
+		&db.timeline()[6].event,
+		DBEvent::CoinFlip {
+			flip: Flip::Legacy { heads: true }
+		}
+	));
+}
+
+#[test]
+fn loads_v5_journal() {
+	let (_dir, db) = open_fixture("v5.jsonl", "db.jsonl");
+
+	assert_fixture_state(&db, GuildId::new(3));
+
+	assert_eq!(db.timeline()[1].meta.channel, Some(ChannelId::new(9)));
+	assert_eq!(db.timeline()[1].meta.message, None);
+
+	// Messages from before anti-spam only follow the old rule
+	assert!(matches!(
+		db.timeline()[1].event,
+		DBEvent::UserSendMessage {
+			fingerprint: None,
+			legacy_rules: true,
+			..
+		}
+	));
+
+	assert!(matches!(
+		&db.timeline()[6].event,
+		DBEvent::CoinFlip {
+			flip: Flip::Fair(_)
+		}
+	));
+}
+
+#[test]
+fn loads_v6_journal() {
+	let (_dir, db) = open_fixture("v6.jsonl", "db.jsonl");
+
+	assert_fixture_state(&db, GuildId::new(3));
+
+	assert_eq!(db.timeline()[1].meta.message, None);
+
+	assert!(matches!(
+		db.timeline()[1].event,
+		DBEvent::UserSendMessage {
+			fingerprint: Some(1311768467463790320),
+			legacy_rules: false,
+			..
+		}
+	));
+}
+
+#[test]
+fn loads_v7_journal() {
+	let (_dir, db) = open_fixture("v7.jsonl", "db.jsonl");
+
+	assert_fixture_state(&db, GuildId::new(3));
+
+	assert_eq!(db.timeline()[0].meta.message, None);
+	assert_eq!(db.timeline()[1].meta.message, Some(MessageId::new(21)));
+}
+
+#[test]
+fn events_without_meta_are_upgraded_as_unknown() {
+	let (_dir, db) = open_fixture("v1.jsonl", "db.jsonl");
+
+	for envelope in db.timeline() {
+		assert_eq!(envelope.meta.source, EventSource::Unknown);
+		assert_eq!(envelope.meta.actor, None);
+		assert_eq!(envelope.meta.guild, Some(get_legacy_guild()));
+		assert_eq!(envelope.meta.timestamp.unix_timestamp(), 0);
+	}
+}
+
+#[test]
+fn old_events_are_rewritten_as_current_version() {
+	let (dir, mut db) = open_fixture("v0.jsonl", "db.jsonl");
+
+	db.add(
+		DBEvent::Counter {
+			user: UserId::new(5),
+		},
+		EventMeta::now(EventSource::Command("counter".to_string()), None, None),
+	)
+	.unwrap();
+
+	let journal = std::fs::read_to_string(dir.path().join("db.jsonl")).unwrap();
+	let last = journal.lines().last().unwrap();
+
+	assert!(last.starts_with(&format!("{{\"version\":{}", schema::CURRENT_VERSION)));
+}
+
+#[test]
+fn rejects_events_from_the_future() {
+	let raw = format!(
+		"{{\"version\":{},\"event\":{{\"Counter\":{{\"user\":\"1\"}}}}}}",
+		schema::CURRENT_VERSION + 1
+	);
+
+	assert!(schema::decode(raw.as_bytes()).is_err());
+}
+
+#[test]
+fn sqlite_reads_what_the_journal_reads() {
+	let dir = tempfile::tempdir().unwrap();
+
+	let mut db = Database::new(Box::new(
+		SqliteStorage::open(&dir.path().join("db.sqlite")).unwrap(),
+	))
+	.unwrap();
+
+	for line in std::fs::read_to_string("tests/fixtures/v0.jsonl")
+		.unwrap()
+		.lines()
+	{
+		let envelope = schema::decode(line.as_bytes()).unwrap();
+
+		db.add(envelope.event, envelope.meta).unwrap();
+	}
+
+	drop(db);
+
+	let db = Database::new(Box::new(
+		SqliteStorage::open(&dir.path().join("db.sqlite")).unwrap(),
+	))
+	.unwrap();
+
+	assert_fixture_state(&db, get_legacy_guild());
+}
+
+#[test]
+fn snapshots_from_before_guilds_go_to_the_legacy_guild() {
+	let dir = tempfile::tempdir().unwrap();
+	let path = dir.path().join("db.jsonl.snapshot");
+
+	std::fs::write(
+		&path,
+		r#"{"compacted":0,"offset":0,"state":{"counter":4,"people_who_counted":["5"],"flips_in_a_row":2,"users":{},"servers":{},"last_typed_user":"5"}}"#,
+	)
+	.unwrap();
+
+	let state = Snapshot::load(&path).unwrap().unwrap().state;
+
+	assert_eq!(state.global.counter, 0);
+	assert_eq!(state.progress(Some(get_legacy_guild())).counter, 4);
+	assert_eq!(state.progress(Some(get_legacy_guild())).flips_in_a_row, 2);
+}
+
+#[test]
+fn progress_is_kept_per_guild_unless_global() {
+	let dir = tempfile::tempdir().unwrap();
+
+	let mut db = common::database(dir.path());
+
+	let count_in = |db: &mut Database, guild: u64| {
+		db.add(
+			DBEvent::Counter {
+				user: UserId::new(5),
+			},
+			EventMeta::now(
+				EventSource::Command("counter".to_string()),
+				Some(UserId::new(5)),
+				Some(GuildId::new(guild)),
+			),
+		)
+		.unwrap();
+	};
+
+	count_in(&mut db, 10);
+	count_in(&mut db, 10);
+	count_in(&mut db, 11);
+
+	assert_eq!(db.state().progress(Some(GuildId::new(10))).counter, 2);
+	assert_eq!(db.state().progress(Some(GuildId::new(11))).counter, 1);
+
+	for guild in [10, 11] {
+		db.add(
+			DBEvent::SetGlobalProfile {
+				server: GuildId::new(guild),
+				enabled: true,
+			},
+			EventMeta::now(
+				EventSource::Command("admin_global_profile".to_string()),
+				None,
+				None,
+			),
+		)
+		.unwrap();
+	}
+
+	count_in(&mut db, 10);
+	count_in(&mut db, 11);
+
+	assert_eq!(db.state().global.counter, 2);
+
+	// The guild's own progress is still there if it stops sharing
+	assert_eq!(db.state().servers[&GuildId::new(10)].progress.counter, 2);
+}
+
+#[test]
+fn exported_state_imports_into_a_fresh_database() {
+	let (_dir, db) = open_fixture("v2.jsonl", "db.jsonl");
+
+	let exported = serde_json::to_string(db.state()).unwrap();
+
+	let dir = tempfile::tempdir().unwrap();
+	let open = || {
+		Database::new(Box::new(
+			SqliteStorage::open(&dir.path().join("db.sqlite")).unwrap(),
+		))
+		.unwrap()
+	};
+
+	let mut fresh = open();
+
+	fresh
+		.import(
+			schema::decode_state(serde_json::from_str(&exported).unwrap()).unwrap(),
+			EventMeta::now(EventSource::System("import".to_string()), None, None),
+		)
+		.unwrap();
+
+	// Importing twice would throw away what's there
+	assert!(fresh
+		.import(
+			Default::default(),
+			EventMeta::now(EventSource::System("import".to_string()), None, None)
+		)
+		.is_err());
+
+	drop(fresh);
+
+	let fresh = open();
+
+	assert_eq!(fresh.len(), 1);
+	assert_fixture_state_in(fresh.state(), GuildId::new(3));
+	assert_eq!(
+		serde_json::to_value(fresh.state()).unwrap(),
+		serde_json::from_str::<serde_json::Value>(&exported).unwrap()
+	);
+}
+
+#[test]
+fn failing_events_are_rejected_but_old_ones_still_replay() {
+	let (dir, mut db) = open_fixture("v2.jsonl", "db.jsonl");
+
+	let burn = DBEvent::AdminBurn {
+		user: UserId::new(5),
+		item: InventoryItem::ScytheVivi,
+	};
+	let meta = EventMeta::now(
+		EventSource::Command("admin_burn".to_string()),
+		None,
+		Some(GuildId::new(3)),
+	);
+
+	let err = db.add(burn.clone(), meta.clone()).unwrap_err();
+
+	assert!(matches!(
+		err.downcast_ref::<ReduceError>(),
+		Some(ReduceError::User(_))
+	));
+	assert_eq!(db.len(), 8);
+
+	drop(db);
+
+	// Before failing events were rejected, they were written anyway
+	let mut journal = std::fs::read_to_string(dir.path().join("db.jsonl")).unwrap();
+	journal += &schema::encode(&EventEnvelope { meta, event: burn }).unwrap();
+	journal.push('\n');
+	std::fs::write(dir.path().join("db.jsonl"), journal).unwrap();
+
+	let db = common::database(dir.path());
+
+	assert_eq!(db.len(), 9);
+	assert_fixture_state_in(db.state(), GuildId::new(3));
+}
@@ -0,0 +1,121 @@
+use quicksilver::data::{
+	envelope::{EventEnvelope, EventMeta, EventSource},
+	items::InventoryItem,
+	leaderboard::{rank, LeaderboardScope},
+	leveling::{LevelCurve, Leveling},
+	state::{DBEvent, DBState},
+};
+use serenity::all::{GuildId, Timestamp, UserId};
+
+const GUILD: GuildId = GuildId::new(1);
+
+fn apply(state: &DBState, event: DBEvent) -> DBState {
+	EventEnvelope {
+		meta: EventMeta::now(EventSource::Unknown, None, Some(GUILD)),
+		event,
+	}
+	.reduce_state(state)
+	.unwrap()
+}
+
+/// `count` messages worth 15 XP from each of `users`, taking turns
+fn messages(state: DBState, users: &[u64], count: usize) -> DBState {
+	(0..count).fold(state, |state, _| {
+		users.iter().fold(state, |state, user| {
+			apply(
+				&state,
+				DBEvent::UserSendMessage {
+					user: UserId::new(*user),
+					length: 100,
+					fingerprint: None,
+					legacy_rules: true,
+				},
+			)
+		})
+	})
+}
+
+#[test]
+fn ending_a_season_archives_rewards_and_resets() {
+	let state = messages(DBState::default(), &[11, 12], 10);
+	let state = messages(state, &[13, 12], 1);
+
+	let state = apply(
+		&state,
+		DBEvent::EndSeason {
+			rewards: vec![InventoryItem::ScytheVivi, InventoryItem::Stick],
+		},
+	);
+
+	let progress = state.progress(Some(GUILD));
+
+	assert_eq!(progress.season(), 2);
+
+	let record = &progress.seasons[0];
+
+	assert_eq!(record.number, 1);
+	assert_eq!(
+		record
+			.standings
+			.iter()
+			.map(|x| x.user.get())
+			.collect::<Vec<_>>(),
+		[12, 11, 13]
+	);
+	assert_eq!(
+		(record.standings[0].level, record.standings[0].xp),
+		(2, 165)
+	);
+
+	let user = |id| state.get_user_or_default(Some(GUILD), &UserId::new(id));
+
+	// The best two got the rewards
+	assert_eq!(user(12).items, [InventoryItem::ScytheVivi]);
+	assert_eq!(user(11).items, [InventoryItem::Stick]);
+	assert!(user(13).items.is_empty());
+
+	// Everyone starts over, but keeps their lifetime XP
+	assert_eq!((user(12).level, user(12).this_levels_xp), (1, 0));
+	assert_eq!((user(12).season_xp(), user(12).total_xp), (0, 165));
+}
+
+#[test]
+fn leaderboards_show_the_season_and_lifetime() {
+	let state = messages(DBState::default(), &[11, 12], 10);
+	let state = apply(&state, DBEvent::EndSeason { rewards: vec![] });
+	let state = messages(state, &[13, 14], 1);
+
+	let now = Timestamp::now();
+
+	let season = rank(&state, Some(GUILD), LeaderboardScope::Guild, &now);
+	let lifetime = rank(&state, Some(GUILD), LeaderboardScope::Lifetime, &now);
+
+	assert_eq!(season[0].0, UserId::new(13));
+	assert_eq!(
+		lifetime.iter().map(|x| x.0.get()).collect::<Vec<_>>(),
+		[11, 12, 13, 14]
+	);
+}
+
+#[test]
+fn curve_changes_only_recompute_the_running_season() {
+	let state = messages(DBState::default(), &[11, 12], 10);
+	let state = apply(&state, DBEvent::EndSeason { rewards: vec![] });
+	let state = messages(state, &[11, 12], 2);
+
+	let state = apply(
+		&state,
+		DBEvent::SetLeveling {
+			server: GUILD,
+			leveling: Leveling {
+				curve: LevelCurve::Linear { base: 10, step: 0 },
+				..Default::default()
+			},
+		},
+	);
+
+	let user = state.get_user_or_default(Some(GUILD), &UserId::new(11));
+
+	assert_eq!((user.level, user.this_levels_xp), (4, 0));
+	assert_eq!(user.total_xp, 180);
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use quicksilver::data::{
+	envelope::{EventMeta, EventSource},
+	state::DBEvent,
+	storage::StorageBackend,
+	Database,
+};
+use serenity::all::{GuildId, UserId};
+
+#[test]
+fn counters_from_many_users_validate() {
+	let dir = tempfile::tempdir().unwrap();
+	let mut db = common::database(dir.path());
+
+	for user in 20..32 {
+		db.add(
+			DBEvent::Counter {
+				user: UserId::new(user),
+			},
+			EventMeta::now(
+				EventSource::Unknown,
+				Some(UserId::new(user)),
+				Some(GuildId::new(3)),
+			),
+		)
+		.unwrap();
+	}
+
+	db.validate().unwrap();
+
+	// And once more from a snapshot, which is how the admin CLI sees it
+	db.snapshot().unwrap();
+	drop(db);
+
+	common::database(dir.path()).validate().unwrap();
+}
+
+#[test]
+fn inspecting_leaves_the_files_alone() {
+	let dir = tempfile::tempdir().unwrap();
+
+	for backend in [StorageBackend::Journal, StorageBackend::Sqlite] {
+		let path = dir.path().join(format!("{backend:?}"));
+
+		assert!(backend.open_read_only(&path).is_err());
+		assert!(!path.exists());
+
+		let mut db = Database::new(backend.open_at(&path).unwrap()).unwrap();
+
+		db.add(
+			DBEvent::Counter {
+				user: UserId::new(20),
+			},
+			EventMeta::now(EventSource::Unknown, None, Some(GuildId::new(3))),
+		)
+		.unwrap();
+
+		drop(db);
+
+		// SQLite readers can still leave an empty WAL around, but that's all
+		let files = || std::fs::read(&path).unwrap();
+
+		let before = files();
+
+		let db = Database::inspect(backend.open_read_only(&path).unwrap()).unwrap();
+		assert_eq!(db.state().progress(Some(GuildId::new(3))).counter, 1);
+		drop(db);
+
+		assert_eq!(files(), before, "{backend:?}");
+	}
+
+	// A torn last line is skipped, but not cut off
+	let path = dir.path().join("Journal");
+	std::fs::write(&path, std::fs::read_to_string(&path).unwrap() + "{\"vers").unwrap();
+
+	let db = Database::inspect(StorageBackend::Journal.open_read_only(&path).unwrap()).unwrap();
+	assert_eq!(db.len(), 1);
+
+	assert!(std::fs::read_to_string(&path).unwrap().ends_with("{\"vers"));
+}
@@ -0,0 +1,143 @@
+use quicksilver::{
+	data::{
+		envelope::{EventEnvelope, EventMeta, EventSource},
+		state::{DBEvent, DBState},
+	},
+	systems::voice_xp::{VoiceMember, VoiceTracker},
+};
+use serenity::all::{ChannelId, GuildId, Timestamp, UserId};
+
+const GUILD: GuildId = GuildId::new(1);
+const VC: ChannelId = ChannelId::new(10);
+const AFK: ChannelId = ChannelId::new(11);
+
+fn at(minutes: i64) -> Timestamp { Timestamp::from_unix_timestamp(minutes * 60).unwrap() }
+
+fn member(user: u64, channel: ChannelId) -> VoiceMember {
+	VoiceMember {
+		user: UserId::new(user),
+		channel,
+		deaf: false,
+		muted: false,
+		bot: false,
+	}
+}
+
+/// Minutes user 1 was active for since the last update
+fn minutes(tracker: &mut VoiceTracker, members: &[VoiceMember], minute: i64) -> u64 {
+	tracker
+		.update(GUILD, members, Some(AFK), at(minute))
+		.into_iter()
+		.filter(|x| x.user == UserId::new(1))
+		.map(|x| x.active_minutes)
+		.sum()
+}
+
+#[test]
+fn talking_with_someone_counts() {
+	let mut tracker = VoiceTracker::default();
+
+	minutes(&mut tracker, &[member(1, VC)], 0);
+	minutes(&mut tracker, &[member(1, VC), member(2, VC)], 5);
+
+	assert_eq!(minutes(&mut tracker, &[member(2, VC)], 15), 10);
+}
+
+#[test]
+fn no_xp_when_alone_deafened_muted_or_afk() {
+	let mut tracker = VoiceTracker::default();
+
+	let bot = VoiceMember {
+		bot: true,
+		..member(3, VC)
+	};
+
+	// Bots aren't company
+	minutes(&mut tracker, &[member(1, VC), bot], 0);
+	assert_eq!(minutes(&mut tracker, &[bot], 10), 0);
+
+	// Neither is being deafened
+	let deaf = VoiceMember {
+		deaf: true,
+		..member(1, VC)
+	};
+
+	minutes(&mut tracker, &[deaf, member(2, VC)], 20);
+	minutes(&mut tracker, &[member(1, VC), member(2, VC)], 30);
+	assert_eq!(minutes(&mut tracker, &[member(2, VC)], 32), 2);
+
+	// Being muted stops it too, though they're still company for others
+	let muted = VoiceMember {
+		muted: true,
+		..member(1, VC)
+	};
+
+	minutes(&mut tracker, &[muted, member(2, VC)], 33);
+	assert_eq!(minutes(&mut tracker, &[member(2, VC)], 38), 0);
+
+	// Or the AFK channel
+	minutes(&mut tracker, &[member(1, AFK), member(2, AFK)], 40);
+	assert_eq!(minutes(&mut tracker, &[], 60), 0);
+}
+
+#[test]
+fn moving_channels_ends_the_session() {
+	let mut tracker = VoiceTracker::default();
+	let other = ChannelId::new(12);
+
+	minutes(&mut tracker, &[member(1, VC), member(2, VC)], 0);
+
+	let accrued = tracker
+		.update(GUILD, &[member(1, other), member(2, VC)], Some(AFK), at(3))
+		.into_iter()
+		.filter(|x| x.user == UserId::new(1))
+		.collect::<Vec<_>>();
+
+	assert_eq!(accrued.len(), 1);
+	assert_eq!(accrued[0].channel, VC);
+	assert_eq!(accrued[0].started, at(0));
+	assert_eq!(accrued[0].active_minutes, 3);
+}
+
+#[test]
+fn long_stays_count_before_they_end() {
+	let mut tracker = VoiceTracker::default();
+
+	minutes(&mut tracker, &[member(1, VC), member(2, VC)], 0);
+
+	let flushed = |tracker: &mut VoiceTracker, seconds| {
+		tracker
+			.flush(Timestamp::from_unix_timestamp(seconds).unwrap())
+			.into_iter()
+			.filter(|x| x.user == UserId::new(1))
+			.map(|x| x.active_minutes)
+			.sum::<u64>()
+	};
+
+	assert_eq!(flushed(&mut tracker, 5 * 60 + 30), 5);
+
+	// The leftover half minute isn't lost
+	assert_eq!(flushed(&mut tracker, 10 * 60), 5);
+	assert_eq!(minutes(&mut tracker, &[member(2, VC)], 12), 2);
+}
+
+#[test]
+fn sessions_replay_into_xp() {
+	let state = EventEnvelope {
+		meta: EventMeta::now(EventSource::Unknown, Some(UserId::new(1)), Some(GUILD)),
+		event: DBEvent::VoiceSession {
+			user: UserId::new(1),
+			started: at(0),
+			active_minutes: 30,
+		},
+	}
+	.reduce_state(&DBState::default())
+	.unwrap();
+
+	assert_eq!(
+		state
+			.get_user_or_default(Some(GUILD), &UserId::new(1))
+			.total_xp,
+		60
+	);
+}